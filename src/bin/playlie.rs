@@ -1,20 +1,406 @@
-use tokio;
-use reqwest;
+use futures::{Stream, StreamExt, TryStreamExt};
+use playlie::lastfm::{self, errors, Artist, Playlist, PlaylistItem, SimilarTrack};
+use playlie::playlist::{self, ExportedPlaylist, ExportedTrack};
+use std::io::Write;
+use std::str::FromStr;
 
-use playlie::lastfm;
+const USAGE: &str = "usage: playlie [--limit N] [--format FORMAT] [--verbose] [username]
+       playlie [--limit N] [--format FORMAT] [--verbose] --seed ARTIST TRACK
+
+Print the tracks Last.fm recommends for a user, or generate a playlist of
+tracks similar to a seed track.
+
+  username          the user to recommend tracks for, defaulting to
+                    $LASTFM_USER
+  --seed ARTIST TRACK
+                    generate a playlist similar to TRACK by ARTIST instead
+  --limit N         print at most N tracks, rather than all of them. When
+                    generating, this is the length of the playlist, which
+                    defaults to 20
+  --format FORMAT   print the tracks as text (the default), json, m3u, or
+                    ndjson (one JSON object per line). Generated tracks are
+                    printed as ndjson as soon as each is found
+  --verbose         print the URL of each request to stderr, with the API
+                    key masked";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Format {
+    /// One `artist - track` line per track
+    #[default]
+    Text,
+    Json,
+    M3u,
+    /// One JSON object per track. Generated tracks are written as each is
+    /// found.
+    Ndjson,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "m3u" => Ok(Format::M3u),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => Err(format!("unknown format: {}", s)),
+        }
+    }
+}
+
+/// Credit a track to its artists, such as "Dolly Parton & Kenny Rogers".
+fn join_artists(artists: &[Artist]) -> String {
+    playlist::artist_names(artists).join(" & ")
+}
+
+/// Render a playlist in the given format.
+fn render(playlist: &Playlist, format: Format) -> String {
+    match format {
+        Format::Text => playlist
+            .playlist
+            .iter()
+            .map(|item| format!("{} - {}\n", join_artists(&item.artists), item.name))
+            .collect(),
+        Format::Json => ExportedPlaylist::from(playlist).to_json() + "\n",
+        Format::M3u => ExportedPlaylist::from(playlist).to_m3u(),
+        Format::Ndjson => playlist
+            .playlist
+            .iter()
+            .map(|item| {
+                serde_json::to_string(&ExportedTrack::from(item))
+                    .expect("track is always serializable")
+                    + "\n"
+            })
+            .collect(),
+    }
+}
+
+/// Write each track of `tracks` to `out` as a line of JSON as soon as it is
+/// received, flushing after each line so that downstream tools see it
+/// immediately. Writing stops at the first error.
+async fn write_ndjson<S, W>(
+    mut tracks: S,
+    out: &mut W,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+where
+    S: Stream<Item = Result<ExportedTrack, errors::Error>> + Unpin,
+    W: Write,
+{
+    while let Some(track) = tracks.next().await {
+        serde_json::to_writer(&mut *out, &track?)?;
+        out.write_all(b"\n")?;
+        out.flush()?;
+    }
+
+    Ok(())
+}
+
+/// The length of a generated playlist when no `--limit` is given.
+const DEFAULT_LENGTH: usize = 20;
+
+/// A generated track, credited to its artist.
+fn similar_item(track: SimilarTrack) -> PlaylistItem {
+    PlaylistItem {
+        name: track.name,
+        artists: vec![track.artist],
+        url: track.url,
+    }
+}
+
+/// A playlist of generated tracks.
+fn similar_playlist(tracks: Vec<SimilarTrack>) -> Playlist {
+    Playlist {
+        playlist: tracks.into_iter().map(similar_item).collect(),
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    user: Option<String>,
+    /// The artist and name of the track to generate a playlist from
+    seed: Option<(String, String)>,
+    limit: Option<usize>,
+    format: Format,
+    verbose: bool,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--limit" => {
+                let limit = args.next().ok_or("--limit requires a value")?;
+                let limit = limit
+                    .parse()
+                    .map_err(|_| format!("invalid limit: {}", limit))?;
+                parsed.limit = Some(limit);
+            }
+            "--format" => {
+                parsed.format = args.next().ok_or("--format requires a value")?.parse()?;
+            }
+            "--seed" => {
+                let artist = args.next().ok_or("--seed requires an artist and track")?;
+                let track = args.next().ok_or("--seed requires an artist and track")?;
+                parsed.seed = Some((artist, track));
+            }
+            "--verbose" => parsed.verbose = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown option: {}", flag)),
+            _ if parsed.user.is_none() => parsed.user = Some(arg),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+    if parsed.seed.is_some() && parsed.user.is_some() {
+        return Err("a username cannot be combined with --seed".to_owned());
+    }
+
+    Ok(parsed)
+}
+
+/// The user to recommend tracks for, given as an argument or by the
+/// `LASTFM_USER` environment variable.
+fn username(user: Option<String>) -> Result<String, String> {
+    user.or_else(|| std::env::var("LASTFM_USER").ok())
+        .filter(|user| !user.is_empty())
+        .ok_or_else(|| "no username given and LASTFM_USER is not set".to_owned())
+}
+
+fn exit_with_usage(err: String) -> ! {
+    eprintln!("playlie: {}\n\n{}", err, USAGE);
+    std::process::exit(2);
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let args = parse_args(std::env::args().skip(1)).unwrap_or_else(|e| exit_with_usage(e));
     let api_key = std::env::var("LASTFM_API_KEY").expect("LASTFM_API_KEY must be set");
-    let http_client = reqwest::Client::new();
-    let lfm = lastfm::Client::new(&api_key, &http_client);
+    let mut builder = lastfm::Client::builder(api_key);
+    if args.verbose {
+        builder = builder.on_request(|req| eprintln!("playlie: {} {}", req.method, req.url));
+    }
+    let lfm = builder.build();
 
-    let res = lfm.user_recommended("sebnow").await?;
+    let res = match args.seed {
+        Some((artist, track)) => {
+            let length = args.limit.unwrap_or(DEFAULT_LENGTH);
+            let tracks = lfm.generate_playlist_stream(&artist, &track, length);
+            if args.format == Format::Ndjson {
+                let tracks = tracks.map_ok(|t| ExportedTrack::from(&similar_item(t)));
+                return write_ndjson(tracks, &mut std::io::stdout().lock()).await;
+            }
+            similar_playlist(tracks.try_collect().await?)
+        }
+        None => {
+            let user = username(args.user).unwrap_or_else(|e| exit_with_usage(e));
+            let mut res = lfm.user_recommended(&user).await?;
+            if let Some(limit) = args.limit {
+                res.playlist.truncate(limit);
+            }
+            res
+        }
+    };
 
-    for item in res.playlist {
-        let artist: Vec<&str> = item.artists.iter().map(|a| a.name.as_str()).collect();
-        println!("{} - {}", artist.join(" & "), item.name);
-    }
+    print!("{}", render(&res, args.format));
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<Args, String> {
+        parse_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn parses_user_and_limit() {
+        assert_eq!(args(&[]), Ok(Args::default()));
+        assert_eq!(
+            args(&["--limit", "20", "sebnow"]),
+            Ok(Args {
+                user: Some("sebnow".into()),
+                limit: Some(20),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            args(&["sebnow", "--limit", "5"]),
+            Ok(Args {
+                user: Some("sebnow".into()),
+                limit: Some(5),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            args(&["--format", "m3u"]),
+            Ok(Args {
+                format: Format::M3u,
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            args(&["--format", "ndjson"]).unwrap().format,
+            Format::Ndjson
+        );
+        assert_eq!(
+            args(&["--seed", "Cher", "Believe", "--limit", "10"]),
+            Ok(Args {
+                seed: Some(("Cher".into(), "Believe".into())),
+                limit: Some(10),
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            args(&["--verbose", "sebnow"]),
+            Ok(Args {
+                user: Some("sebnow".into()),
+                verbose: true,
+                ..Args::default()
+            })
+        );
+    }
+
+    fn playlist() -> Playlist {
+        Playlist {
+            playlist: vec![PlaylistItem {
+                name: "Islands in the Stream".into(),
+                artists: vec![
+                    Artist {
+                        name: "Dolly Parton".into(),
+                        images: Default::default(),
+                        url: None,
+                    },
+                    Artist {
+                        name: "Kenny Rogers".into(),
+                        images: Default::default(),
+                        url: None,
+                    },
+                ],
+                url: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn renders_each_format() {
+        assert_eq!(
+            render(&playlist(), Format::Text),
+            "Dolly Parton & Kenny Rogers - Islands in the Stream\n"
+        );
+        assert_eq!(
+            render(&playlist(), Format::Json),
+            r#"{"tracks":[{"artists":["Dolly Parton","Kenny Rogers"],"name":"Islands in the Stream"}]}"#
+                .to_owned()
+                + "\n"
+        );
+        assert_eq!(
+            render(&playlist(), Format::M3u),
+            "#EXTM3U\n\
+             #EXTINF:-1,Dolly Parton & Kenny Rogers - Islands in the Stream\n\
+             https://www.last.fm/music/Dolly%20Parton/_/Islands%20in%20the%20Stream\n"
+        );
+        assert_eq!(
+            render(&playlist(), Format::Ndjson),
+            "{\"artists\":[\"Dolly Parton\",\"Kenny Rogers\"],\"name\":\"Islands in the Stream\"}\n"
+        );
+    }
+
+    /// Records what had been written each time it was flushed.
+    #[derive(Default)]
+    struct Flushes {
+        buf: Vec<u8>,
+        flushed: Vec<String>,
+    }
+
+    impl Write for Flushes {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.buf.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.flushed
+                .push(String::from_utf8(self.buf.clone()).unwrap());
+            Ok(())
+        }
+    }
+
+    fn exported(artist: &str, name: &str) -> ExportedTrack {
+        ExportedTrack {
+            artists: vec![artist.into()],
+            name: name.into(),
+            match_score: None,
+            url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn streams_ndjson_line_by_line() {
+        let tracks = futures::stream::iter(vec![
+            Ok(exported("Cher", "Believe")),
+            Ok(exported("Madonna", "Vogue")),
+            Err(errors::Error::InvalidInput("page 2".into())),
+            Ok(exported("ABBA", "Waterloo")),
+        ]);
+        let mut out = Flushes::default();
+
+        let err = write_ndjson(tracks, &mut out).await.unwrap_err();
+
+        assert_eq!(err.to_string(), "invalid input: page 2");
+        assert_eq!(
+            out.flushed,
+            vec![
+                "{\"artists\":[\"Cher\"],\"name\":\"Believe\"}\n",
+                "{\"artists\":[\"Cher\"],\"name\":\"Believe\"}\n\
+                 {\"artists\":[\"Madonna\"],\"name\":\"Vogue\"}\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn credits_generated_tracks_to_their_artist() {
+        let track = SimilarTrack {
+            name: "Believe".into(),
+            artist: Artist {
+                name: "Cher".into(),
+                images: Default::default(),
+                url: None,
+            },
+            match_score: 1.0,
+            playcount: None,
+            mbid: None,
+            url: Some("https://www.last.fm/music/Cher/_/Believe".into()),
+            images: Default::default(),
+        };
+
+        assert_eq!(
+            render(&similar_playlist(vec![track]), Format::Text),
+            "Cher - Believe\n"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_args() {
+        assert!(args(&["--limit"]).is_err());
+        assert!(args(&["--limit", "many"]).is_err());
+        assert!(args(&["--quiet"]).is_err());
+        assert!(args(&["--format", "xml"]).is_err());
+        assert!(args(&["sebnow", "rj"]).is_err());
+        assert!(args(&["--seed", "Cher"]).is_err());
+        assert!(args(&["sebnow", "--seed", "Cher", "Believe"]).is_err());
+    }
+
+    #[test]
+    fn joins_distinct_artists() {
+        let artists: Vec<_> = ["Cher", " Cher ", "Madonna", "Cher"]
+            .iter()
+            .map(|name| Artist {
+                name: name.to_string(),
+                images: Default::default(),
+                url: None,
+            })
+            .collect();
+
+        assert_eq!(join_artists(&artists), "Cher & Madonna");
+    }
+}
@@ -0,0 +1,376 @@
+use serde::Deserialize;
+
+use super::search::SearchResponse;
+use super::{de, errors, require, Client, HttpClient, Images, Paged, Tag, TopTags};
+
+/// Detailed information about an album, as returned by `album.getInfo`.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(from = "RawAlbumInfo")]
+pub struct AlbumInfo {
+    pub name: String,
+    pub artist: String,
+    pub listeners: u64,
+    pub playcount: u64,
+    /// The album's tracklist in order
+    pub tracks: Vec<AlbumTrack>,
+    /// Names of the album's most applied tags
+    pub tags: Vec<String>,
+    /// The album's cover art
+    pub images: Images,
+}
+
+#[derive(Deserialize)]
+struct RawAlbumInfo {
+    name: String,
+    artist: String,
+    #[serde(deserialize_with = "de::number")]
+    listeners: u64,
+    #[serde(deserialize_with = "de::number")]
+    playcount: u64,
+    #[serde(default)]
+    tracks: AlbumTracks,
+    #[serde(default, deserialize_with = "de::tag_names")]
+    tags: Vec<String>,
+    #[serde(rename = "image", default, deserialize_with = "de::images")]
+    images: Images,
+}
+
+#[derive(Deserialize, Default)]
+struct AlbumTracks {
+    #[serde(default, deserialize_with = "de::one_or_many")]
+    track: Vec<AlbumTrack>,
+}
+
+impl From<RawAlbumInfo> for AlbumInfo {
+    fn from(raw: RawAlbumInfo) -> Self {
+        AlbumInfo {
+            name: raw.name,
+            artist: raw.artist,
+            listeners: raw.listeners,
+            playcount: raw.playcount,
+            tracks: raw.tracks.track,
+            tags: raw.tags,
+            images: raw.images,
+        }
+    }
+}
+
+/// A track on an album's tracklist.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(from = "RawAlbumTrack")]
+pub struct AlbumTrack {
+    pub name: String,
+    /// Length of the track in seconds, if known
+    pub duration: Option<u64>,
+    /// Position of the track on the album, starting at 1
+    pub rank: u32,
+}
+
+#[derive(Deserialize)]
+struct RawAlbumTrack {
+    name: String,
+    #[serde(default, deserialize_with = "de::option_number")]
+    duration: Option<u64>,
+    #[serde(rename = "@attr")]
+    attr: AlbumTrackAttr,
+}
+
+#[derive(Deserialize)]
+struct AlbumTrackAttr {
+    #[serde(deserialize_with = "de::number")]
+    rank: u32,
+}
+
+impl From<RawAlbumTrack> for AlbumTrack {
+    fn from(raw: RawAlbumTrack) -> Self {
+        AlbumTrack {
+            name: raw.name,
+            duration: raw.duration,
+            rank: raw.attr.rank,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Album {
+    album: AlbumInfo,
+}
+
+/// An album matching a search, as returned by `album.search`, or one of the
+/// most popular albums for a tag, as returned by `tag.getTopAlbums`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct AlbumMatch {
+    pub name: String,
+    #[serde(deserialize_with = "de::artist_name")]
+    pub artist: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    /// The album's page on Last.fm
+    pub url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct AlbumMatches {
+    #[serde(rename = "albummatches")]
+    album_matches: InnerAlbumMatches,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerAlbumMatches {
+    #[serde(default, deserialize_with = "de::one_or_many")]
+    album: Vec<AlbumMatch>,
+}
+
+impl From<AlbumMatches> for Vec<AlbumMatch> {
+    fn from(matches: AlbumMatches) -> Self {
+        matches.album_matches.album
+    }
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Fetch information about an album, including its tracklist.
+    pub async fn album_info(&self, artist: &str, album: &str) -> Result<AlbumInfo, errors::Error> {
+        require("artist", artist)?;
+        require("album", album)?;
+
+        let url = self.build_as_uri("album.getInfo", &[("artist", artist), ("album", album)]);
+
+        Ok(self.get::<Album>(url).await?.album)
+    }
+
+    /// Fetch the tags most often applied to an album.
+    pub async fn album_top_tags(
+        &self,
+        artist: &str,
+        album: &str,
+    ) -> Result<Vec<Tag>, errors::Error> {
+        require("artist", artist)?;
+        require("album", album)?;
+
+        let url = self.build_as_uri("album.getTopTags", &[("artist", artist), ("album", album)]);
+
+        Ok(self.get::<TopTags>(url).await?.top_tags.tags)
+    }
+
+    /// Fetch a page of the albums matching a query, best match first.
+    pub async fn search_album(
+        &self,
+        query: &str,
+        page: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Paged<AlbumMatch>, errors::Error> {
+        require("album", query)?;
+
+        let mut params = vec![("album", query.to_owned())];
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        let url = self.build_as_uri("album.search", &params);
+
+        Ok(self
+            .get::<SearchResponse<AlbumMatches>>(url)
+            .await?
+            .into_paged())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::mock_client;
+    use crate::lastfm::Pagination;
+    use serde_json::json;
+    use wiremock::matchers::query_param;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn deserialize_album_info() {
+        let json = json!({"album": {
+            "artist": "Slowdive",
+            "mbid": "",
+            "tags": {"tag": [
+                {"url": "https://www.last.fm/tag/shoegaze", "name": "shoegaze"},
+                {"url": "https://www.last.fm/tag/dream+pop", "name": "dream pop"}
+            ]},
+            "playcount": "9331061",
+            "image": [],
+            "tracks": {"track": [{
+                "streamable": {"fulltrack": "0", "#text": "0"},
+                "duration": 372,
+                "url": "https://www.last.fm/music/Slowdive/_/Alison",
+                "name": "Alison",
+                "@attr": {"rank": 1},
+                "artist": {"url": "https://www.last.fm/music/Slowdive", "name": "Slowdive", "mbid": ""}
+            }, {
+                "duration": null,
+                "url": "https://www.last.fm/music/Slowdive/_/Machine+Gun",
+                "name": "Machine Gun",
+                "@attr": {"rank": 2},
+                "artist": {"url": "https://www.last.fm/music/Slowdive", "name": "Slowdive", "mbid": ""}
+            }]},
+            "url": "https://www.last.fm/music/Slowdive/Souvlaki",
+            "name": "Souvlaki",
+            "listeners": "502043"
+        }});
+
+        let album: Album = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            album.album,
+            AlbumInfo {
+                name: "Souvlaki".into(),
+                artist: "Slowdive".into(),
+                listeners: 502043,
+                playcount: 9331061,
+                tracks: vec![
+                    AlbumTrack {
+                        name: "Alison".into(),
+                        duration: Some(372),
+                        rank: 1,
+                    },
+                    AlbumTrack {
+                        name: "Machine Gun".into(),
+                        duration: None,
+                        rank: 2,
+                    },
+                ],
+                tags: vec!["shoegaze".into(), "dream pop".into()],
+                images: Default::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_single_track_album() {
+        let json = json!({"album": {
+            "artist": "Slowdive",
+            "name": "Shallow Water",
+            "playcount": "10",
+            "listeners": "5",
+            "tracks": {"track": {
+                "duration": 240,
+                "name": "Shallow Water",
+                "@attr": {"rank": 1}
+            }}
+        }});
+
+        let album: Album = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            album.album.tracks,
+            vec![AlbumTrack {
+                name: "Shallow Water".into(),
+                duration: Some(240),
+                rank: 1,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn search_album_matches() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "album.search"))
+            .and(query_param("album", "believe"))
+            .and(query_param("page", "2"))
+            .and(query_param("limit", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"results": {
+                "opensearch:Query": {"#text": "", "role": "request", "searchTerms": "believe", "startPage": "2"},
+                "opensearch:totalResults": "5",
+                "opensearch:startIndex": "2",
+                "opensearch:itemsPerPage": "2",
+                "albummatches": {"album": [{
+                    "name": "Believe",
+                    "artist": "Cher",
+                    "url": "https://www.last.fm/music/Cher/Believe",
+                    "image": [{"#text": "https://example.com/34s.png", "size": "small"}],
+                    "streamable": "0",
+                    "mbid": "63b3a8ca-26f2-4e2b-b867-647a6ec2bebd"
+                }, {
+                    "name": "Believe",
+                    "artist": "Justin Bieber",
+                    "url": "https://www.last.fm/music/Justin+Bieber/Believe",
+                    "image": [],
+                    "streamable": "0",
+                    "mbid": ""
+                }]},
+                "@attr": {"for": "believe"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let res = mock_client(&server)
+            .search_album("believe", Some(2), Some(2))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res,
+            Paged {
+                items: vec![
+                    AlbumMatch {
+                        name: "Believe".into(),
+                        artist: "Cher".into(),
+                        mbid: Some("63b3a8ca-26f2-4e2b-b867-647a6ec2bebd".into()),
+                        url: "https://www.last.fm/music/Cher/Believe".into(),
+                    },
+                    AlbumMatch {
+                        name: "Believe".into(),
+                        artist: "Justin Bieber".into(),
+                        mbid: None,
+                        url: "https://www.last.fm/music/Justin+Bieber/Believe".into(),
+                    },
+                ],
+                pagination: Pagination {
+                    page: 2,
+                    per_page: 2,
+                    total_pages: 3,
+                    total: 5,
+                },
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn album_top_tags_single_tag() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "album.getTopTags"))
+            .and(query_param("artist", "Slowdive"))
+            .and(query_param("album", "Souvlaki"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"toptags": {
+                "tag": {"count": 100, "name": "shoegaze", "url": "https://www.last.fm/tag/shoegaze"},
+                "@attr": {"artist": "Slowdive", "album": "Souvlaki"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tags = mock_client(&server)
+            .album_top_tags("Slowdive", "Souvlaki")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tags,
+            vec![Tag {
+                name: "shoegaze".into(),
+                count: 100,
+                reach: 0,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn search_album_rejects_empty_query() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "album.search"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server)
+            .search_album("", None, None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("album")));
+    }
+}
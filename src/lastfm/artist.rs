@@ -0,0 +1,546 @@
+use serde::Deserialize;
+
+use super::search::SearchResponse;
+use super::{de, errors, require, Artist, Client, HttpClient, Images, Paged, Pages, Tag, TopTags};
+
+/// Detailed information about an artist, as returned by `artist.getInfo`.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(from = "RawArtistInfo")]
+pub struct ArtistInfo {
+    pub name: String,
+    pub mbid: Option<String>,
+    pub url: Option<String>,
+    pub listeners: u64,
+    pub playcount: u64,
+    pub bio_summary: Option<String>,
+    /// Artists similar to this one
+    pub similar: Vec<Artist>,
+    pub images: Images,
+}
+
+#[derive(Deserialize)]
+struct RawArtistInfo {
+    name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    mbid: Option<String>,
+    url: Option<String>,
+    stats: ArtistStats,
+    #[serde(default, deserialize_with = "de::wiki_summary")]
+    bio: Option<String>,
+    #[serde(default)]
+    similar: SimilarArtistList,
+    #[serde(rename = "image", default, deserialize_with = "de::images")]
+    images: Images,
+}
+
+#[derive(Deserialize)]
+struct ArtistStats {
+    #[serde(deserialize_with = "de::number")]
+    listeners: u64,
+    #[serde(deserialize_with = "de::number")]
+    playcount: u64,
+}
+
+#[derive(Deserialize, Default)]
+struct SimilarArtistList {
+    #[serde(default, deserialize_with = "de::one_or_many")]
+    artist: Vec<Artist>,
+}
+
+impl From<RawArtistInfo> for ArtistInfo {
+    fn from(raw: RawArtistInfo) -> Self {
+        ArtistInfo {
+            name: raw.name,
+            mbid: raw.mbid,
+            url: raw.url,
+            listeners: raw.stats.listeners,
+            playcount: raw.stats.playcount,
+            bio_summary: raw.bio,
+            similar: raw.similar.artist,
+            images: raw.images,
+        }
+    }
+}
+
+/// An artist similar to another, as returned by `artist.getSimilar`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct SimilarArtist {
+    pub name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    /// How similar the artist is to the seed artist, from 0 to 1
+    #[serde(rename = "match", deserialize_with = "de::number")]
+    pub match_score: f64,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct SimilarArtists {
+    #[serde(rename = "similarartists")]
+    similar_artists: InnerSimilarArtists,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct InnerSimilarArtists {
+    #[serde(rename = "artist", default, deserialize_with = "de::one_or_many")]
+    artists: Vec<SimilarArtist>,
+}
+
+/// One of an artist's most played tracks, as returned by
+/// `artist.getTopTracks`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct TopTrack {
+    pub name: String,
+    #[serde(deserialize_with = "de::number")]
+    pub playcount: u64,
+    #[serde(deserialize_with = "de::number")]
+    pub listeners: u64,
+    /// Position of the track in the artist's chart, starting at 1
+    #[serde(rename = "@attr", deserialize_with = "de::rank")]
+    pub rank: u32,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct TopTracks {
+    #[serde(rename = "toptracks")]
+    top_tracks: InnerTopTracks,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct InnerTopTracks {
+    #[serde(rename = "track", default, deserialize_with = "de::one_or_many")]
+    tracks: Vec<TopTrack>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ArtistMatches {
+    #[serde(rename = "artistmatches")]
+    artist_matches: InnerArtistMatches,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerArtistMatches {
+    #[serde(default, deserialize_with = "de::one_or_many")]
+    artist: Vec<Artist>,
+}
+
+impl From<ArtistMatches> for Vec<Artist> {
+    fn from(matches: ArtistMatches) -> Self {
+        matches.artist_matches.artist
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct ArtistInfoResponse {
+    artist: ArtistInfo,
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Fetch detailed information about an artist.
+    pub async fn artist_info(&self, artist: &str) -> Result<ArtistInfo, errors::Error> {
+        require("artist", artist)?;
+
+        let url = self.build_as_uri("artist.getInfo", &[("artist", artist)]);
+
+        Ok(self.get::<ArtistInfoResponse>(url).await?.artist)
+    }
+
+    pub async fn similar_artists(&self, artist: &str) -> Result<Vec<SimilarArtist>, errors::Error> {
+        self.fetch_similar_artists(artist, None).await
+    }
+
+    /// Fetch at most `limit` artists similar to the given artist.
+    pub async fn similar_artists_limited(
+        &self,
+        artist: &str,
+        limit: u32,
+    ) -> Result<Vec<SimilarArtist>, errors::Error> {
+        self.fetch_similar_artists(artist, Some(limit)).await
+    }
+
+    async fn fetch_similar_artists(
+        &self,
+        artist: &str,
+        limit: Option<u32>,
+    ) -> Result<Vec<SimilarArtist>, errors::Error> {
+        require("artist", artist)?;
+
+        let mut params = vec![("artist", artist.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        let url = self.build_as_uri("artist.getSimilar", &params);
+
+        Ok(self
+            .get::<SimilarArtists>(url)
+            .await?
+            .similar_artists
+            .artists)
+    }
+
+    /// Fetch an artist's most played tracks, ordered by rank.
+    pub async fn artist_top_tracks(
+        &self,
+        artist: &str,
+        limit: Option<u32>,
+    ) -> Result<Vec<TopTrack>, errors::Error> {
+        require("artist", artist)?;
+
+        let mut params = vec![("artist", artist.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        let url = self.build_as_uri("artist.getTopTracks", &params);
+
+        Ok(self.get::<TopTracks>(url).await?.top_tracks.tracks)
+    }
+
+    /// Fetch the tags most often applied to an artist.
+    pub async fn artist_top_tags(&self, artist: &str) -> Result<Vec<Tag>, errors::Error> {
+        require("artist", artist)?;
+
+        let url = self.build_as_uri("artist.getTopTags", &[("artist", artist)]);
+
+        Ok(self.get::<TopTags>(url).await?.top_tags.tags)
+    }
+
+    /// Search for artists by name.
+    pub async fn search_artist(
+        &self,
+        query: &str,
+        page: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Paged<Artist>, errors::Error> {
+        require("artist", query)?;
+
+        let mut params = vec![("artist", query.to_owned())];
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        let url = self.build_as_uri("artist.search", &params);
+
+        Ok(self
+            .get::<SearchResponse<ArtistMatches>>(url)
+            .await?
+            .into_paged())
+    }
+
+    /// Stream all of the artists matching a query, best match first.
+    pub fn search_artist_pages<'a>(&'a self, query: &'a str) -> Pages<'a, Artist> {
+        Pages::new(move |page| self.search_artist(query, Some(page), None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::mock_client;
+    use crate::lastfm::Pagination;
+    use serde_json::json;
+    use wiremock::matchers::{query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn deserialize_artist_info() {
+        let json = json!({"artist": {
+            "name": "Cher",
+            "mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818",
+            "url": "https://www.last.fm/music/Cher",
+            "image": [{"#text": "https://example.com/34s.png", "size": "small"}],
+            "streamable": "0",
+            "ontour": "0",
+            "stats": {"listeners": "1791662", "playcount": "44682424"},
+            "similar": {"artist": [
+                {"name": "Madonna", "url": "https://www.last.fm/music/Madonna", "image": []},
+                {"name": "Kylie Minogue", "url": "https://www.last.fm/music/Kylie+Minogue", "image": []}
+            ]},
+            "tags": {"tag": [{"name": "pop", "url": "https://www.last.fm/tag/pop"}]},
+            "bio": {
+                "links": {"link": {"#text": "", "rel": "original", "href": "https://last.fm/music/Cher/+wiki"}},
+                "published": "15 Jan 2006, 22:33",
+                "summary": "Cher is an American singer and actress.",
+                "content": "Cher is an American singer and actress. She is known as the Goddess of Pop."
+            }
+        }});
+
+        let info: ArtistInfoResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            info.artist,
+            ArtistInfo {
+                name: "Cher".into(),
+                mbid: Some("bfcc6d75-a6a5-4bc6-8282-47aec8531818".into()),
+                url: Some("https://www.last.fm/music/Cher".into()),
+                listeners: 1791662,
+                playcount: 44682424,
+                bio_summary: Some("Cher is an American singer and actress.".into()),
+                similar: vec![
+                    Artist {
+                        name: "Madonna".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Madonna".into()),
+                    },
+                    Artist {
+                        name: "Kylie Minogue".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Kylie+Minogue".into()),
+                    },
+                ],
+                images: Images {
+                    small: Some("https://example.com/34s.png".into()),
+                    ..Default::default()
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_similar_artists() {
+        let json = json!({"similarartists": {
+            "artist": [{
+                "name": "Madonna",
+                "mbid": "79239441-bfd5-4981-a70c-55c3f15c1287",
+                "match": "1",
+                "url": "https://www.last.fm/music/Madonna",
+                "image": [],
+                "streamable": "0"
+            }, {
+                "name": "Kylie Minogue",
+                "match": "0.523",
+                "url": "https://www.last.fm/music/Kylie+Minogue",
+                "image": [],
+                "streamable": "0"
+            }],
+            "@attr": {"artist": "Cher"}
+        }});
+
+        let artists: SimilarArtists = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            artists.similar_artists.artists,
+            vec![
+                SimilarArtist {
+                    name: "Madonna".into(),
+                    mbid: Some("79239441-bfd5-4981-a70c-55c3f15c1287".into()),
+                    match_score: 1.0,
+                },
+                SimilarArtist {
+                    name: "Kylie Minogue".into(),
+                    mbid: None,
+                    match_score: 0.523,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn similar_artists_limited_sends_limit() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "artist.getSimilar"))
+            .and(query_param("artist", "Cher"))
+            .and(query_param("limit", "10"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similarartists": {"artist": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let artists = mock_client(&server)
+            .similar_artists_limited("Cher", 10)
+            .await
+            .unwrap();
+        assert!(artists.is_empty());
+    }
+
+    #[test]
+    fn deserialize_top_tracks() {
+        let json = json!({"toptracks": {
+            "track": [{
+                "name": "Believe",
+                "playcount": "3965577",
+                "listeners": "673862",
+                "mbid": "32ca187e-ee25-4f18-b7d0-3b6713f24635",
+                "url": "https://www.last.fm/music/Cher/_/Believe",
+                "streamable": "0",
+                "artist": {"name": "Cher", "url": "https://www.last.fm/music/Cher"},
+                "image": [],
+                "@attr": {"rank": "1"}
+            }, {
+                "name": "Strong Enough",
+                "playcount": "1019200",
+                "listeners": "237306",
+                "url": "https://www.last.fm/music/Cher/_/Strong+Enough",
+                "streamable": "0",
+                "artist": {"name": "Cher", "url": "https://www.last.fm/music/Cher"},
+                "image": [],
+                "@attr": {"rank": "2"}
+            }],
+            "@attr": {"artist": "Cher", "page": "1", "perPage": "2", "totalPages": "500", "total": "1000"}
+        }});
+
+        let tracks: TopTracks = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            tracks.top_tracks.tracks,
+            vec![
+                TopTrack {
+                    name: "Believe".into(),
+                    playcount: 3965577,
+                    listeners: 673862,
+                    rank: 1,
+                },
+                TopTrack {
+                    name: "Strong Enough".into(),
+                    playcount: 1019200,
+                    listeners: 237306,
+                    rank: 2,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn artist_top_tracks_sends_limit_only_when_given() {
+        let server = MockServer::start().await;
+        let empty = json!({"toptracks": {"track": []}});
+        Mock::given(query_param("method", "artist.getTopTracks"))
+            .and(query_param("limit", "5"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&empty))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(query_param("method", "artist.getTopTracks"))
+            .and(query_param_is_missing("limit"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&empty))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        client.artist_top_tracks("Cher", Some(5)).await.unwrap();
+        client.artist_top_tracks("Cher", None).await.unwrap();
+    }
+
+    #[test]
+    fn deserialize_top_tags() {
+        let json = json!({"toptags": {
+            "tag": [
+                {"count": 100, "name": "pop", "url": "https://www.last.fm/tag/pop"},
+                {"count": 43, "name": "female vocalists", "url": "https://www.last.fm/tag/female+vocalists"},
+                {"count": 0, "name": "seen live", "url": "https://www.last.fm/tag/seen+live"}
+            ],
+            "@attr": {"artist": "Cher"}
+        }});
+
+        let tags: TopTags = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            tags.top_tags.tags,
+            vec![
+                Tag {
+                    name: "pop".into(),
+                    count: 100,
+                    reach: 0,
+                },
+                Tag {
+                    name: "female vocalists".into(),
+                    count: 43,
+                    reach: 0,
+                },
+                Tag {
+                    name: "seen live".into(),
+                    count: 0,
+                    reach: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_artist_search() {
+        let json = json!({"results": {
+            "opensearch:Query": {"#text": "", "role": "request", "searchTerms": "cher", "startPage": "2"},
+            "opensearch:totalResults": "2870",
+            "opensearch:startIndex": "2",
+            "opensearch:itemsPerPage": "2",
+            "artistmatches": {"artist": [
+                {
+                    "name": "Cher",
+                    "listeners": "1791662",
+                    "mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818",
+                    "url": "https://www.last.fm/music/Cher",
+                    "streamable": "0",
+                    "image": []
+                },
+                {
+                    "name": "Cher Lloyd",
+                    "listeners": "580938",
+                    "mbid": "",
+                    "url": "https://www.last.fm/music/Cher+Lloyd",
+                    "streamable": "0",
+                    "image": []
+                }
+            ]},
+            "@attr": {"for": "cher"}
+        }});
+
+        let res: SearchResponse<ArtistMatches> = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            res.into_paged(),
+            Paged {
+                items: vec![
+                    Artist {
+                        name: "Cher".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Cher".into()),
+                    },
+                    Artist {
+                        name: "Cher Lloyd".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Cher+Lloyd".into()),
+                    },
+                ],
+                pagination: Pagination {
+                    page: 2,
+                    per_page: 2,
+                    total_pages: 1435,
+                    total: 2870,
+                },
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn search_artist_sends_pagination() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "artist.search"))
+            .and(query_param("artist", "cher"))
+            .and(query_param("page", "2"))
+            .and(query_param("limit", "30"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"results": {
+                "opensearch:totalResults": "0",
+                "opensearch:startIndex": "30",
+                "opensearch:itemsPerPage": "30",
+                "artistmatches": {"artist": []}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let res = mock_client(&server)
+            .search_artist("cher", Some(2), Some(30))
+            .await
+            .unwrap();
+        assert!(res.items.is_empty());
+        assert_eq!(res.pagination.page, 2);
+        assert_eq!(res.pagination.start_index(), 30);
+    }
+
+    #[tokio::test]
+    async fn search_artist_rejects_empty_query() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "artist.search"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server)
+            .search_artist(" ", None, None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("artist")));
+    }
+}
@@ -0,0 +1,283 @@
+//! Signing and authentication for the authenticated methods of the web
+//! service.
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use super::{errors, require, Client, HttpClient};
+
+/// An authenticated session, as returned by `auth.getSession`. The key does
+/// not expire and can be stored to call authenticated methods on the user's
+/// behalf.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Session {
+    /// Name of the user who approved the session
+    pub name: String,
+    pub key: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SessionResponse {
+    session: Session,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    token: String,
+}
+
+/// Compute the `api_sig` of a request, as described in
+/// <https://www.last.fm/api/authspec#_8-signing-calls>.
+///
+/// The parameters are sorted by name and concatenated as `namevalue` pairs,
+/// the shared secret is appended, and the result is MD5 hashed. The `format`
+/// and `callback` parameters must not be included.
+pub(crate) fn sign_params<V: AsRef<str>>(params: &[(&str, V)], secret: &str) -> String {
+    let mut sorted: Vec<_> = params.iter().collect();
+    sorted.sort_by_key(|(k, _)| *k);
+
+    let mut payload = String::new();
+    for (k, v) in sorted {
+        payload.push_str(k);
+        payload.push_str(v.as_ref());
+    }
+    payload.push_str(secret);
+
+    format!("{:x}", md5::compute(payload))
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Build the parameters of a signed request, including the method, API
+    /// key, and signature.
+    fn signed_params<'a, V: AsRef<str>>(
+        &'a self,
+        method: &'a str,
+        params: &'a [(&'a str, V)],
+    ) -> Result<Vec<(&'a str, String)>, errors::Error> {
+        let secret = self
+            .secret
+            .as_deref()
+            .ok_or(errors::Error::MissingCredentials("secret"))?;
+
+        let mut signed = vec![
+            ("method", method.to_owned()),
+            ("api_key", self.api_key.clone()),
+        ];
+        signed.extend(params.iter().map(|(k, v)| (*k, v.as_ref().to_owned())));
+        let sig = sign_params(&signed, secret);
+        signed.push(("api_sig", sig));
+
+        Ok(signed)
+    }
+
+    /// Like [`Client::build_as_uri`], but signs the request with the shared
+    /// secret.
+    fn build_signed_uri<V: AsRef<str>>(
+        &self,
+        method: &str,
+        params: &[(&str, V)],
+    ) -> Result<reqwest::Url, errors::Error> {
+        let signed = self.signed_params(method, params)?;
+        let mut url = self.as_base_url.clone();
+        url.query_pairs_mut()
+            .extend_pairs(signed.iter())
+            .append_pair("format", "json");
+
+        Ok(url)
+    }
+
+    /// Call an authenticated write method with a signed POST request on
+    /// behalf of the user with the session key `session_key`.
+    pub(crate) async fn post_signed<T: DeserializeOwned, V: AsRef<str>>(
+        &self,
+        method: &str,
+        session_key: &str,
+        params: &[(&str, V)],
+    ) -> Result<T, errors::Error> {
+        require("session_key", session_key)?;
+
+        let mut params: Vec<(&str, &str)> = params.iter().map(|(k, v)| (*k, v.as_ref())).collect();
+        params.push(("sk", session_key));
+        let mut form = self.signed_params(method, &params)?;
+        form.push(("format", "json".to_owned()));
+        let form = form.into_iter().map(|(k, v)| (k.to_owned(), v)).collect();
+        let request =
+            self.http_request(reqwest::Method::POST, self.as_base_url.clone(), Some(form));
+
+        self.send(request).await
+    }
+
+    /// Fetch an unauthorized request token to start the desktop
+    /// authentication flow. The user must approve it at
+    /// [`Client::auth_url`] before it can be exchanged for a session with
+    /// [`Client::get_session`].
+    pub async fn get_token(&self) -> Result<String, errors::Error> {
+        let url = self.build_signed_uri::<&str>("auth.getToken", &[])?;
+
+        Ok(self.get::<TokenResponse>(url).await?.token)
+    }
+
+    /// The page at which the user approves a request token.
+    pub fn auth_url(&self, token: &str) -> reqwest::Url {
+        let mut url = self.last_fm_base_url.clone();
+        url.path_segments_mut()
+            .expect("last.fm base URL must be a base")
+            .pop_if_empty()
+            .extend(&["api", "auth", ""]);
+        url.query_pairs_mut()
+            .append_pair("api_key", &self.api_key)
+            .append_pair("token", token);
+
+        url
+    }
+
+    /// Exchange an approved request token for a session.
+    pub async fn get_session(&self, token: &str) -> Result<Session, errors::Error> {
+        require("token", token)?;
+
+        let url = self.build_signed_uri("auth.getSession", &[("token", token)])?;
+
+        Ok(self.get::<SessionResponse>(url).await?.session)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::{mock_client, signing_client};
+    use crate::lastfm::ClientBuilder;
+    use serde_json::json;
+    use wiremock::matchers::{query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn signs_sorted_params_with_secret() {
+        let sig = sign_params(
+            &[
+                ("token", "yyyyyyyyyy"),
+                ("method", "auth.getSession"),
+                ("api_key", "xxxxxxxxxx"),
+            ],
+            "ilovecher",
+        );
+
+        assert_eq!(sig, "dc8837f76dba853ce9f2b0be643b1505");
+    }
+
+    #[test]
+    fn auth_url_includes_key_and_token() {
+        let client = ClientBuilder::new("key").build();
+
+        assert_eq!(
+            client.auth_url("token").as_str(),
+            "https://www.last.fm/api/auth/?api_key=key&token=token"
+        );
+    }
+
+    #[tokio::test]
+    async fn get_token_is_signed() {
+        let server = MockServer::start().await;
+        let sig = sign_params(&[("method", "auth.getToken"), ("api_key", "key")], "secret");
+        Mock::given(query_param("method", "auth.getToken"))
+            .and(query_param("api_sig", sig.as_str()))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"token": "cf45fe5a3e3cebad5a518d2346dcf0f6"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let token = signing_client(&server).get_token().await.unwrap();
+
+        assert_eq!(token, "cf45fe5a3e3cebad5a518d2346dcf0f6");
+    }
+
+    #[cfg(feature = "cache")]
+    #[tokio::test]
+    async fn signed_requests_are_not_cached() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "auth.getToken"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"token": "cf45fe5a3e3cebad5a518d2346dcf0f6"})),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = ClientBuilder::new("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .secret("secret")
+            .cache(crate::lastfm::ResponseCache::new(
+                std::num::NonZeroUsize::new(16).unwrap(),
+                std::time::Duration::from_secs(60),
+            ))
+            .build();
+
+        client.get_token().await.unwrap();
+        client.get_token().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_session_exchanges_token() {
+        let server = MockServer::start().await;
+        let sig = sign_params(
+            &[
+                ("method", "auth.getSession"),
+                ("api_key", "key"),
+                ("token", "token"),
+            ],
+            "secret",
+        );
+        Mock::given(query_param("method", "auth.getSession"))
+            .and(query_param("token", "token"))
+            .and(query_param("api_sig", sig.as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"session": {
+                "name": "sebnow",
+                "key": "d580d57f32848f5dcf574d1ce18d78b2",
+                "subscriber": 0
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let session = signing_client(&server).get_session("token").await.unwrap();
+
+        assert_eq!(
+            session,
+            Session {
+                name: "sebnow".into(),
+                key: "d580d57f32848f5dcf574d1ce18d78b2".into(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn get_session_rejects_empty_token() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "auth.getSession"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let err = signing_client(&server).get_session(" ").await.unwrap_err();
+
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("token")));
+    }
+
+    #[tokio::test]
+    async fn signed_requests_require_secret() {
+        let server = MockServer::start().await;
+        Mock::given(query_param_is_missing("api_sig"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server).get_session("token").await.unwrap_err();
+
+        assert!(matches!(err, errors::Error::MissingCredentials("secret")));
+    }
+}
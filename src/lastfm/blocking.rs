@@ -0,0 +1,348 @@
+//! A synchronous [`Client`], for programs which do not run an async runtime.
+//!
+//! ```no_run
+//! use playlie::lastfm::blocking;
+//!
+//! let client = blocking::Client::builder("api-key").build_blocking();
+//! let tracks = client.similar_tracks("Cher", "Believe")?;
+//! # Ok::<(), playlie::lastfm::errors::Error>(())
+//! ```
+
+use std::sync::Arc;
+
+use super::errors::Error;
+use super::{
+    AlbumInfo, AlbumMatch, Artist, ArtistInfo, ChartArtist, ChartTrack, ChartWeek, ClientBuilder,
+    Corrected, Correction, Friend, LibraryArtist, LovedTrack, NowPlaying, Paged, Period, Playlist,
+    RecentTrack, RecentTracksOptions, Scrobble, ScrobbleResult, Session, SimilarArtist,
+    SimilarTrack, SimilarTracksOptions, Tag, TagInfo, TagTrack, TaggedItem, TaggingType, TopTrack,
+    TrackInfo, TrackMatch, UserInfo, UserTopArtist, UserTopTrack, WeeklyTrack,
+};
+
+/// A client of the Last.fm API which blocks the calling thread until each
+/// request completes.
+///
+/// Each method mirrors the method of the same name on the async
+/// [`Client`](super::Client), which does the work on a runtime owned by this
+/// client. Paginated streams, such as
+/// [`search_artist_pages`](super::Client::search_artist_pages), are not
+/// available.
+#[derive(Clone, Debug)]
+pub struct Client {
+    inner: super::Client<reqwest::blocking::Client>,
+    runtime: Arc<tokio::runtime::Runtime>,
+}
+
+/// Define blocking methods which run the async method of the same name to
+/// completion.
+macro_rules! blocking {
+    ($($(#[$attr:meta])* fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            $(#[$attr])*
+            pub fn $name(&self $(, $arg: $ty)*) -> Result<$ret, Error> {
+                self.runtime.block_on(self.inner.$name($($arg),*))
+            }
+        )*
+    };
+}
+
+impl Client {
+    pub fn new(api_key: &str, client: &reqwest::blocking::Client) -> Self {
+        Client::owned(api_key.to_owned(), client.clone())
+    }
+
+    /// Create a client which takes ownership of the API key and HTTP client.
+    pub fn owned(api_key: String, http: reqwest::blocking::Client) -> Self {
+        Client::from_async(ClientBuilder::new(api_key).build_with(http))
+    }
+
+    /// Configure a client, creating it with
+    /// [`ClientBuilder::build_blocking`].
+    pub fn builder(api_key: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(api_key)
+    }
+
+    pub(crate) fn from_async(inner: super::Client<reqwest::blocking::Client>) -> Self {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("blocking client runtime must start");
+
+        Client {
+            inner,
+            runtime: Arc::new(runtime),
+        }
+    }
+
+    /// See [`Client::with_key`](super::Client::with_key).
+    pub fn with_key(&self, api_key: impl Into<String>) -> Self {
+        Client {
+            inner: self.inner.with_key(api_key),
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    /// See [`Client::auth_url`](super::Client::auth_url).
+    pub fn auth_url(&self, token: &str) -> reqwest::Url {
+        self.inner.auth_url(token)
+    }
+
+    /// See [`Client::similar_tracks_many`](super::Client::similar_tracks_many).
+    pub fn similar_tracks_many(
+        &self,
+        seeds: &[(String, String)],
+        concurrency: usize,
+    ) -> Vec<Result<Vec<SimilarTrack>, Error>> {
+        self.runtime
+            .block_on(self.inner.similar_tracks_many(seeds, concurrency))
+    }
+
+    /// See [`Client::scrobble`](super::Client::scrobble).
+    pub fn scrobble(
+        &self,
+        session_key: &str,
+        scrobbles: &[Scrobble],
+    ) -> Vec<Result<Vec<ScrobbleResult>, Error>> {
+        self.runtime
+            .block_on(self.inner.scrobble(session_key, scrobbles))
+    }
+
+    blocking! {
+        fn album_info(&self, artist: &str, album: &str) -> AlbumInfo;
+        fn artist_info(&self, artist: &str) -> ArtistInfo;
+        fn similar_artists(&self, artist: &str) -> Vec<SimilarArtist>;
+        fn similar_artists_limited(&self, artist: &str, limit: u32) -> Vec<SimilarArtist>;
+        fn artist_top_tracks(&self, artist: &str, limit: Option<u32>) -> Vec<TopTrack>;
+        fn artist_top_tags(&self, artist: &str) -> Vec<Tag>;
+        fn album_top_tags(&self, artist: &str, album: &str) -> Vec<Tag>;
+        fn search_album(
+            &self,
+            query: &str,
+            page: Option<u32>,
+            limit: Option<u32>
+        ) -> Paged<AlbumMatch>;
+        fn search_artist(
+            &self,
+            query: &str,
+            page: Option<u32>,
+            limit: Option<u32>
+        ) -> Paged<Artist>;
+        fn get_token(&self) -> String;
+        fn library_artists(
+            &self,
+            user: &str,
+            limit: Option<u32>,
+            page: Option<u32>
+        ) -> Paged<LibraryArtist>;
+        fn get_session(&self, token: &str) -> Session;
+        fn chart_top_tracks(&self, page: Option<u32>, limit: Option<u32>) -> Vec<ChartTrack>;
+        fn chart_top_artists(&self, page: Option<u32>, limit: Option<u32>) -> Vec<ChartArtist>;
+        fn geo_top_artists(
+            &self,
+            country: &str,
+            limit: Option<u32>,
+            page: Option<u32>
+        ) -> Paged<ChartArtist>;
+        fn geo_top_tracks(
+            &self,
+            country: &str,
+            limit: Option<u32>,
+            page: Option<u32>
+        ) -> Vec<ChartTrack>;
+        fn track_correction(&self, artist: &str, track: &str) -> Option<Correction>;
+        fn artist_correction(&self, artist: &str) -> Option<Correction>;
+        fn generate_playlist(
+            &self,
+            seed_artist: &str,
+            seed_track: &str,
+            length: usize
+        ) -> Vec<SimilarTrack>;
+        fn user_recommended(&self, user: &str) -> Playlist;
+        fn raw(&self, method: &str, params: &[(&str, &str)]) -> serde_json::Value;
+        fn update_now_playing(
+            &self,
+            session_key: &str,
+            artist: &str,
+            track: &str,
+            album: Option<&str>,
+            duration: Option<u64>
+        ) -> NowPlaying;
+        fn tag_top_tracks(&self, tag: &str, limit: Option<u32>, page: Option<u32>) -> Vec<TagTrack>;
+        fn top_tags(&self) -> Vec<Tag>;
+        fn tag_top_artists(
+            &self,
+            tag: &str,
+            limit: Option<u32>,
+            page: Option<u32>
+        ) -> Paged<ChartArtist>;
+        fn tag_top_albums(
+            &self,
+            tag: &str,
+            limit: Option<u32>,
+            page: Option<u32>
+        ) -> Paged<AlbumMatch>;
+        fn similar_tags(&self, tag: &str) -> Vec<Tag>;
+        fn tag_info(&self, tag: &str) -> TagInfo;
+        fn similar_tracks(&self, artist: &str, track: &str) -> Vec<SimilarTrack>;
+        fn similar_tracks_with(&self, opts: &SimilarTracksOptions) -> Vec<SimilarTrack>;
+        fn similar_tracks_raw(&self, artist: &str, track: &str) -> serde_json::Value;
+        fn similar_tracks_limited(&self, artist: &str, track: &str, limit: u32) -> Vec<SimilarTrack>;
+        fn similar_tracks_autocorrected(
+            &self,
+            artist: &str,
+            track: &str
+        ) -> Corrected<Vec<SimilarTrack>>;
+        fn similar_tracks_by_mbid(&self, mbid: &str) -> Vec<SimilarTrack>;
+        fn track_info(&self, artist: &str, track: &str) -> TrackInfo;
+        fn track_top_tags(&self, artist: &str, track: &str) -> Vec<Tag>;
+        fn track_top_tags_autocorrected(&self, artist: &str, track: &str) -> Corrected<Vec<Tag>>;
+        fn search_track(
+            &self,
+            track: &str,
+            artist: Option<&str>,
+            page: Option<u32>,
+            limit: Option<u32>
+        ) -> Paged<TrackMatch>;
+        fn love_track(&self, session_key: &str, artist: &str, track: &str) -> ();
+        fn unlove_track(&self, session_key: &str, artist: &str, track: &str) -> ();
+        fn add_track_tags(&self, session_key: &str, artist: &str, track: &str, tags: &[&str]) -> ();
+        fn remove_track_tag(&self, session_key: &str, artist: &str, track: &str, tag: &str) -> ();
+        fn user_top_artists(
+            &self,
+            user: &str,
+            period: Period,
+            limit: Option<u32>
+        ) -> Vec<UserTopArtist>;
+        fn user_top_tracks(
+            &self,
+            user: &str,
+            period: Period,
+            limit: Option<u32>
+        ) -> Vec<UserTopTrack>;
+        fn user_recent_tracks(&self, user: &str, limit: Option<u32>) -> Vec<RecentTrack>;
+        fn user_personal_tags(
+            &self,
+            user: &str,
+            tag: &str,
+            tagging_type: TaggingType,
+            limit: Option<u32>,
+            page: Option<u32>
+        ) -> Paged<TaggedItem>;
+        fn user_recent_tracks_with(&self, user: &str, opts: &RecentTracksOptions) -> Vec<RecentTrack>;
+        fn user_loved_tracks(
+            &self,
+            user: &str,
+            limit: Option<u32>,
+            page: Option<u32>
+        ) -> Paged<LovedTrack>;
+        fn user_weekly_track_chart(
+            &self,
+            user: &str,
+            from: Option<u64>,
+            to: Option<u64>
+        ) -> Vec<WeeklyTrack>;
+        fn user_weekly_chart_list(&self, user: &str) -> Vec<ChartWeek>;
+        fn user_info(&self, user: &str) -> UserInfo;
+        fn user_friends(
+            &self,
+            user: &str,
+            limit: Option<u32>,
+            page: Option<u32>
+        ) -> Paged<Friend>;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::errors::ErrorCode;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// Start a mock server on a runtime of its own, as the blocking client
+    /// must not be used from within one.
+    fn mock_server(response: ResponseTemplate) -> (tokio::runtime::Runtime, MockServer) {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(query_param("method", "track.getsimilar"))
+                .respond_with(response)
+                .mount(&server)
+                .await;
+            server
+        });
+
+        (runtime, server)
+    }
+
+    fn client(server: &MockServer) -> Client {
+        ClientBuilder::new("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .build_blocking()
+    }
+
+    #[test]
+    fn similar_tracks() {
+        let (_runtime, server) = mock_server(ResponseTemplate::new(200).set_body_json(
+            json!({"similartracks": {
+                "track": [{"name": "Strong Enough", "match": 1.0, "artist": {"name": "Cher"}}]
+            }}),
+        ));
+
+        let tracks = client(&server).similar_tracks("Cher", "Believe").unwrap();
+
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].name, "Strong Enough");
+    }
+
+    #[test]
+    fn uses_the_given_http_client() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(header("x-proxy-auth", "token"))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(json!({"similartracks": {"track": []}})),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+            server
+        });
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-proxy-auth", "token".parse().unwrap());
+        let http = reqwest::blocking::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap();
+
+        let client = ClientBuilder::new("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .build_blocking_with(http);
+
+        assert!(client.similar_tracks("Cher", "Believe").unwrap().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "build_blocking_with")]
+    fn rejects_async_http_clients() {
+        ClientBuilder::new("key")
+            .http_client(reqwest::Client::new())
+            .build_blocking();
+    }
+
+    #[test]
+    fn similar_tracks_surfaces_api_errors() {
+        let (_runtime, server) = mock_server(ResponseTemplate::new(200).set_body_json(json!({
+            "error": 6,
+            "message": "Track not found"
+        })));
+
+        let err = client(&server).similar_tracks("Cher", "Nope").unwrap_err();
+
+        assert!(matches!(err, Error::Api(e) if e.error == ErrorCode::InvalidParameters));
+    }
+}
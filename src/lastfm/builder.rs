@@ -0,0 +1,345 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{
+    Client, HttpClient, RequestHook, RequestLog, RetryPolicy, AS_BASE_URL, LAST_FM_BASE_URL,
+};
+#[cfg(feature = "cache")]
+use super::{FileCache, ResponseCache};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const DEFAULT_USER_AGENT: &str = concat!("playlie/", env!("CARGO_PKG_VERSION"));
+
+/// Configures and creates a [`Client`].
+///
+/// ```no_run
+/// use playlie::lastfm::ClientBuilder;
+///
+/// let client = ClientBuilder::new("api-key")
+///     .as_base_url("http://localhost:8080/2.0".parse().unwrap())
+///     .build();
+/// ```
+pub struct ClientBuilder {
+    api_key: String,
+    http: Option<reqwest::Client>,
+    as_base_url: reqwest::Url,
+    last_fm_base_url: reqwest::Url,
+    retry: Option<RetryPolicy>,
+    #[cfg(feature = "cache")]
+    cache: Option<ResponseCache>,
+    #[cfg(feature = "cache")]
+    file_cache: Option<FileCache>,
+    timeout: Duration,
+    user_agent: String,
+    default_limit: Option<u32>,
+    default_page: Option<u32>,
+    on_request: Option<RequestHook>,
+    secret: Option<String>,
+}
+
+impl ClientBuilder {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        ClientBuilder {
+            api_key: api_key.into(),
+            http: None,
+            as_base_url: AS_BASE_URL
+                .parse()
+                .expect("AS_BASE_URL must be a valid URL"),
+            last_fm_base_url: LAST_FM_BASE_URL
+                .parse()
+                .expect("LAST_FM_BASE_URL must be a valid URL"),
+            retry: None,
+            #[cfg(feature = "cache")]
+            cache: None,
+            #[cfg(feature = "cache")]
+            file_cache: None,
+            timeout: DEFAULT_TIMEOUT,
+            user_agent: DEFAULT_USER_AGENT.to_owned(),
+            default_limit: None,
+            default_page: None,
+            on_request: None,
+            secret: None,
+        }
+    }
+
+    /// Use an existing HTTP client rather than creating a new one. Its
+    /// settings are left untouched; only the timeout and `User-Agent` header
+    /// are added to each request.
+    pub fn http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = Some(http);
+        self
+    }
+
+    /// Set the base URL of the audioscrobbler web service, used by all API
+    /// methods.
+    pub fn as_base_url(mut self, url: reqwest::Url) -> Self {
+        self.as_base_url = url;
+        self
+    }
+
+    /// Set the base URL of the last.fm website, used by endpoints which are
+    /// not part of the web service. Defaults to `https://www.last.fm`.
+    pub fn last_fm_base_url(mut self, url: reqwest::Url) -> Self {
+        self.last_fm_base_url = url;
+        self
+    }
+
+    /// Retry requests which fail with a transient error. Requests are not
+    /// retried by default.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Serve repeated requests from `cache`. Only successful responses are
+    /// cached. Responses are not cached by default.
+    #[cfg(feature = "cache")]
+    pub fn cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Persist successful responses to `cache`, so they can be served from
+    /// disk when offline. The in-memory cache, if any, is checked first.
+    #[cfg(feature = "cache")]
+    pub fn file_cache(mut self, cache: FileCache) -> Self {
+        self.file_cache = Some(cache);
+        self
+    }
+
+    /// Set how long to wait for each request to complete. Defaults to 10
+    /// seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the `User-Agent` header sent with each request. Defaults to
+    /// `playlie/<version>`.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Set the number of items fetched by list methods which are not given a
+    /// `limit`. Without one, Last.fm's own default is used.
+    pub fn default_limit(mut self, limit: u32) -> Self {
+        self.default_limit = Some(limit);
+        self
+    }
+
+    /// Set the page fetched by list methods which are not given a `page`.
+    /// Without one, the first page is fetched.
+    pub fn default_page(mut self, page: u32) -> Self {
+        self.default_page = Some(page);
+        self
+    }
+
+    /// Call `hook` with the method and URL of each request before it is sent,
+    /// such as to log it, including each retry. The API key and session key
+    /// are masked in the URL.
+    pub fn on_request(mut self, hook: impl Fn(&RequestLog) + Send + Sync + 'static) -> Self {
+        self.on_request = Some(Arc::new(hook));
+        self
+    }
+
+    /// Set the shared secret used to sign requests to authenticated methods.
+    pub fn secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into());
+        self
+    }
+
+    /// Create a client. Unless an HTTP client was given, a new one is created
+    /// which, with the `gzip` feature enabled, requests compressed responses.
+    pub fn build(mut self) -> Client {
+        let http = self.http.take().unwrap_or_else(default_http_client);
+        self.build_with(http)
+    }
+
+    /// Create a synchronous client, with a new HTTP client which, with the
+    /// `gzip` feature enabled, requests compressed responses. To configure
+    /// the HTTP client, such as with a proxy, use
+    /// [`build_blocking_with`](ClientBuilder::build_blocking_with).
+    ///
+    /// Like `reqwest::blocking::Client`, this must not be called from within
+    /// an async runtime.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an async HTTP client was given with
+    /// [`http_client`](ClientBuilder::http_client), as its settings would
+    /// otherwise be silently dropped.
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking(self) -> super::blocking::Client {
+        assert!(
+            self.http.is_none(),
+            "an async HTTP client cannot be used by a blocking client; use build_blocking_with"
+        );
+        let builder = reqwest::blocking::Client::builder();
+        #[cfg(feature = "gzip")]
+        let builder = builder.gzip(true);
+        let http = builder.build().expect("default HTTP client must be valid");
+
+        self.build_blocking_with(http)
+    }
+
+    /// Create a synchronous client which sends its requests with `http`. Its
+    /// settings are left untouched; only the timeout and `User-Agent` header
+    /// are added to each request.
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking_with(self, http: reqwest::blocking::Client) -> super::blocking::Client {
+        super::blocking::Client::from_async(self.build_with(http))
+    }
+
+    /// Create a client which sends its requests with `http`, rather than a
+    /// `reqwest::Client`.
+    pub fn build_with<H: HttpClient>(self, http: H) -> Client<H> {
+        Client {
+            api_key: self.api_key,
+            http,
+            as_base_url: self.as_base_url,
+            last_fm_base_url: self.last_fm_base_url,
+            retry: self.retry,
+            #[cfg(feature = "cache")]
+            cache: self.cache,
+            #[cfg(feature = "cache")]
+            file_cache: self.file_cache,
+            timeout: self.timeout,
+            user_agent: self.user_agent,
+            default_limit: self.default_limit,
+            default_page: self.default_page,
+            on_request: self.on_request,
+            secret: self.secret,
+        }
+    }
+}
+
+fn default_http_client() -> reqwest::Client {
+    let builder = reqwest::Client::builder();
+    #[cfg(feature = "gzip")]
+    let builder = builder.gzip(true);
+
+    builder.build().expect("default HTTP client must be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_to_last_fm() {
+        let client = ClientBuilder::new("key").build();
+
+        assert_eq!(
+            client.as_base_url.as_str(),
+            "http://ws.audioscrobbler.com/2.0"
+        );
+        assert_eq!(client.last_fm_base_url.as_str(), "https://www.last.fm/");
+        assert_eq!(client.timeout, DEFAULT_TIMEOUT);
+        assert_eq!(
+            client.user_agent,
+            format!("playlie/{}", env!("CARGO_PKG_VERSION"))
+        );
+    }
+
+    #[test]
+    fn overrides_base_urls() {
+        let client = ClientBuilder::new("key")
+            .as_base_url("http://localhost:1234/2.0".parse().unwrap())
+            .last_fm_base_url("http://localhost:4321".parse().unwrap())
+            .build();
+
+        assert_eq!(
+            client
+                .build_as_uri("track.getsimilar", &[("mbid", "1")])
+                .as_str(),
+            "http://localhost:1234/2.0?method=track.getsimilar&api_key=key&format=json&mbid=1"
+        );
+        assert_eq!(client.last_fm_base_url.as_str(), "http://localhost:4321/");
+    }
+
+    #[tokio::test]
+    async fn list_methods_use_default_limit_and_page() {
+        use wiremock::matchers::query_param;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "artist.getTopTracks"))
+            .and(query_param("limit", "100"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"toptracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(query_param("method", "artist.getTopTracks"))
+            .and(query_param("limit", "5"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"toptracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(query_param("method", "artist.search"))
+            .and(query_param("limit", "100"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "results": {
+                    "opensearch:totalResults": "0",
+                    "opensearch:startIndex": "100",
+                    "opensearch:itemsPerPage": "100",
+                    "artistmatches": {"artist": []}
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(query_param("method", "artist.getSimilar"))
+            .and(query_param("limit", "100"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(serde_json::json!({"similarartists": {"artist": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ClientBuilder::new("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .default_limit(100)
+            .default_page(2)
+            .build();
+
+        client.artist_top_tracks("Cher", None).await.unwrap();
+        client.artist_top_tracks("Cher", Some(5)).await.unwrap();
+        client.search_artist("Cher", None, None).await.unwrap();
+        client.similar_artists("Cher").await.unwrap();
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn requests_compressed_responses() {
+        use serde_json::json;
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("accept-encoding", "gzip"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = ClientBuilder::new("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .build();
+
+        client.similar_tracks("Cher", "Believe").await.unwrap();
+    }
+}
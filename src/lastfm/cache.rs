@@ -0,0 +1,304 @@
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use lru::LruCache;
+use reqwest::{Method, Url};
+use serde::{Deserialize, Serialize};
+
+use super::{Client, HttpClient};
+
+/// An in-memory cache of successful responses, keyed by the request URL.
+///
+/// At most `capacity` responses are kept, evicting the least recently used
+/// first, and each is served for up to `ttl` after it was fetched. Clones
+/// share the same entries, so a cache can be given to several clients.
+#[derive(Clone)]
+pub struct ResponseCache {
+    entries: Arc<Mutex<LruCache<String, Entry>>>,
+    ttl: Duration,
+}
+
+struct Entry {
+    body: String,
+    fetched: Instant,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
+        ResponseCache {
+            entries: Arc::new(Mutex::new(LruCache::new(capacity))),
+            ttl,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The cached body of the response to `url`, unless it has expired.
+    pub(crate) fn get(&self, url: &str) -> Option<String> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entries.get(url) {
+            Some(entry) if entry.fetched.elapsed() < self.ttl => Some(entry.body.clone()),
+            Some(_) => {
+                entries.pop(url);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, url: &str, body: String) {
+        self.insert_fetched(url, body, Instant::now());
+    }
+
+    /// Cache a response which was fetched at `fetched`, so that it expires
+    /// `ttl` after then rather than after now.
+    pub(crate) fn insert_fetched(&self, url: &str, body: String, fetched: Instant) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.put(url.to_owned(), Entry { body, fetched });
+    }
+}
+
+impl std::fmt::Debug for ResponseCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        f.debug_struct("ResponseCache")
+            .field("capacity", &entries.cap())
+            .field("len", &entries.len())
+            .field("ttl", &self.ttl)
+            .finish()
+    }
+}
+
+/// A cache of successful responses persisted as JSON files in a directory,
+/// named after a hash of the request URL.
+///
+/// Entries older than `ttl` are ignored and refetched, as are entries which
+/// cannot be read or decoded. Failing to write an entry does not fail the
+/// request.
+#[derive(Clone, Debug)]
+pub struct FileCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+#[derive(Deserialize, Serialize)]
+struct FileEntry {
+    /// When the response was fetched, in milliseconds since the Unix epoch
+    fetched_ms: u64,
+    body: String,
+}
+
+impl FileCache {
+    /// Cache responses in `dir`, which is created when the first entry is
+    /// written.
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        FileCache {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        self.dir.join(format!("{:x}.json", md5::compute(url)))
+    }
+
+    /// The cached body of the response to `url`, along with how long ago it
+    /// was fetched, unless it has expired or could not be read.
+    pub(crate) fn get(&self, url: &str) -> Option<(String, Duration)> {
+        let contents = fs::read(self.path(url)).ok()?;
+        let entry: FileEntry = serde_json::from_slice(&contents).ok()?;
+        let age = Duration::from_millis(now_ms().saturating_sub(entry.fetched_ms));
+
+        Some((entry.body, age)).filter(|_| age < self.ttl)
+    }
+
+    pub(crate) fn insert(&self, url: &str, body: String) {
+        let entry = FileEntry {
+            fetched_ms: now_ms(),
+            body,
+        };
+        // Write to a temporary file first so that readers never see a
+        // partially written entry.
+        let path = self.path(url);
+        let tmp = path.with_extension("json.tmp");
+        let written = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&tmp, serde_json::to_vec(&entry)?))
+            .and_then(|_| fs::rename(&tmp, &path));
+
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+    }
+}
+
+/// Milliseconds since the Unix epoch.
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Whether the response to a request may be cached. Only unsigned `GET`
+/// requests are, as signed requests such as `auth.getToken` return a fresh
+/// token or session key each time, which must not be reused or persisted.
+pub(crate) fn cacheable(method: Method, url: &Url) -> bool {
+    method == Method::GET && !url.query_pairs().any(|(k, _)| k == "api_sig")
+}
+
+impl<H: HttpClient> Client<H> {
+    /// The cached body of the response to `url`, from memory or disk.
+    pub(crate) fn cached(&self, url: &str) -> Option<String> {
+        if let Some(body) = self.cache.as_ref().and_then(|c| c.get(url)) {
+            return Some(body);
+        }
+
+        let (body, age) = self.file_cache.as_ref().and_then(|c| c.get(url))?;
+        // Keep the original fetch time, so that reading the entry does not
+        // extend how long it is served for.
+        let fetched = Instant::now().checked_sub(age);
+        if let (Some(cache), Some(fetched)) = (&self.cache, fetched) {
+            cache.insert_fetched(url, body.clone(), fetched);
+        }
+
+        Some(body)
+    }
+
+    /// Cache the body of a successful response to `url`.
+    pub(crate) fn store(&self, url: &str, body: String) {
+        if let Some(cache) = &self.file_cache {
+            cache.insert(url, body.clone());
+        }
+        if let Some(cache) = &self.cache {
+            cache.insert(url, body);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(capacity: usize, ttl: Duration) -> ResponseCache {
+        ResponseCache::new(NonZeroUsize::new(capacity).unwrap(), ttl)
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = cache(2, Duration::from_secs(60));
+        cache.insert("a", "1".into());
+        cache.insert("b", "2".into());
+        assert_eq!(cache.get("a").as_deref(), Some("1"));
+
+        cache.insert("c", "3".into());
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("a").as_deref(), Some("1"));
+        assert_eq!(cache.get("c").as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn expires_entries() {
+        let cache = cache(2, Duration::from_millis(0));
+        cache.insert("a", "1".into());
+
+        assert_eq!(cache.get("a"), None);
+    }
+
+    /// A directory under the system temporary directory which is removed
+    /// when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "playlie-{}-{}-{}",
+                name,
+                std::process::id(),
+                now_ms()
+            ));
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn file_cache_round_trips() {
+        let dir = TempDir::new("round-trip");
+        let cache = FileCache::new(&dir.0, Duration::from_secs(60));
+        assert_eq!(cache.get("http://example.com/a"), None);
+
+        cache.insert("http://example.com/a", "1".into());
+        let body = |cache: &FileCache, url| cache.get(url).map(|(body, _)| body);
+        assert_eq!(body(&cache, "http://example.com/a").as_deref(), Some("1"));
+        assert_eq!(body(&cache, "http://example.com/b"), None);
+
+        let reopened = FileCache::new(&dir.0, Duration::from_secs(60));
+        assert_eq!(
+            body(&reopened, "http://example.com/a").as_deref(),
+            Some("1")
+        );
+    }
+
+    #[test]
+    fn file_cache_serves_sub_second_ttls() {
+        let dir = TempDir::new("sub-second");
+        let cache = FileCache::new(&dir.0, Duration::from_millis(500));
+        cache.insert("http://example.com/a", "1".into());
+
+        assert!(cache.get("http://example.com/a").is_some());
+    }
+
+    #[test]
+    fn file_cache_hits_keep_their_fetch_time() {
+        let dir = TempDir::new("fetch-time");
+        let file_cache = FileCache::new(&dir.0, Duration::from_secs(60));
+        let entry = FileEntry {
+            fetched_ms: now_ms() - 100,
+            body: "1".into(),
+        };
+        fs::create_dir_all(&dir.0).unwrap();
+        fs::write(
+            file_cache.path("http://example.com/a"),
+            serde_json::to_vec(&entry).unwrap(),
+        )
+        .unwrap();
+        let client = crate::lastfm::ClientBuilder::new("key")
+            .cache(cache(2, Duration::from_millis(50)))
+            .file_cache(file_cache)
+            .build();
+
+        assert_eq!(client.cached("http://example.com/a").as_deref(), Some("1"));
+        let memory = client.cache.as_ref().unwrap();
+        assert_eq!(memory.get("http://example.com/a"), None);
+    }
+
+    #[test]
+    fn file_cache_ignores_expired_and_corrupt_entries() {
+        let dir = TempDir::new("invalid");
+        let cache = FileCache::new(&dir.0, Duration::from_secs(0));
+        cache.insert("http://example.com/a", "1".into());
+        assert_eq!(cache.get("http://example.com/a"), None);
+
+        let cache = FileCache::new(&dir.0, Duration::from_secs(60));
+        fs::write(cache.path("http://example.com/a"), "{not json").unwrap();
+        assert_eq!(cache.get("http://example.com/a"), None);
+    }
+}
@@ -0,0 +1,424 @@
+use serde::Deserialize;
+
+use super::{de, errors, require, Artist, Client, HttpClient, Paged, Pagination};
+
+/// A track on a chart, as returned by `chart.getTopTracks` and
+/// `geo.getTopTracks`.
+#[derive(Debug, PartialEq)]
+pub struct ChartTrack {
+    pub name: String,
+    pub artist: Artist,
+    /// Number of plays, or 0 for charts which do not report it such as
+    /// `geo.getTopTracks`
+    pub playcount: u64,
+    pub listeners: u64,
+    /// Position of the track in the chart, starting at 1
+    pub rank: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawChartTrack {
+    name: String,
+    artist: Artist,
+    #[serde(default, deserialize_with = "de::number")]
+    playcount: u64,
+    #[serde(deserialize_with = "de::number")]
+    listeners: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChartTracks {
+    tracks: InnerChartTracks,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerChartTracks {
+    #[serde(rename = "track", default, deserialize_with = "de::one_or_many")]
+    tracks: Vec<RawChartTrack>,
+    #[serde(rename = "@attr")]
+    attr: Pagination,
+}
+
+impl From<ChartTracks> for Vec<ChartTrack> {
+    /// The chart does not include ranks, so they are derived from each
+    /// track's position in the requested page.
+    fn from(chart: ChartTracks) -> Self {
+        let start = chart.tracks.attr.start_index();
+        chart
+            .tracks
+            .tracks
+            .into_iter()
+            .zip(start + 1..)
+            .map(|(t, rank)| ChartTrack {
+                name: t.name,
+                artist: t.artist,
+                playcount: t.playcount,
+                listeners: t.listeners,
+                rank: rank as u32,
+            })
+            .collect()
+    }
+}
+
+/// An artist on a chart, as returned by `chart.getTopArtists`,
+/// `geo.getTopArtists` and `tag.getTopArtists`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ChartArtist {
+    pub name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    /// Number of plays, or 0 for charts which do not report it such as
+    /// `geo.getTopArtists`
+    #[serde(default, deserialize_with = "de::number")]
+    pub playcount: u64,
+    /// Number of listeners, or 0 for charts which do not report it
+    #[serde(default, deserialize_with = "de::number")]
+    pub listeners: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChartArtists {
+    artists: InnerChartArtists,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerChartArtists {
+    #[serde(rename = "artist", default, deserialize_with = "de::one_or_many")]
+    artists: Vec<ChartArtist>,
+}
+
+/// The paginated `{"topartists": {"artist": [...]}}` response shared by
+/// `tag.getTopArtists` and `geo.getTopArtists`.
+#[derive(Deserialize, Debug)]
+pub(super) struct TopArtists {
+    #[serde(rename = "topartists")]
+    top_artists: InnerTopArtists,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerTopArtists {
+    #[serde(rename = "artist", default, deserialize_with = "de::one_or_many")]
+    artists: Vec<ChartArtist>,
+    #[serde(rename = "@attr")]
+    attr: Pagination,
+}
+
+impl From<TopArtists> for Paged<ChartArtist> {
+    fn from(top: TopArtists) -> Self {
+        Paged {
+            items: top.top_artists.artists,
+            pagination: top.top_artists.attr,
+        }
+    }
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Fetch the most popular tracks on Last.fm right now, ordered by rank.
+    pub async fn chart_top_tracks(
+        &self,
+        page: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Vec<ChartTrack>, errors::Error> {
+        let mut params = Vec::new();
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        let url = self.build_as_uri("chart.getTopTracks", &params);
+
+        Ok(self.get::<ChartTracks>(url).await?.into())
+    }
+
+    /// Fetch the most popular artists on Last.fm right now, ordered by rank.
+    pub async fn chart_top_artists(
+        &self,
+        page: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Vec<ChartArtist>, errors::Error> {
+        let mut params = Vec::new();
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        let url = self.build_as_uri("chart.getTopArtists", &params);
+
+        Ok(self.get::<ChartArtists>(url).await?.artists.artists)
+    }
+
+    /// Fetch the most popular tracks in a country, ordered by rank. The
+    /// country is given by its ISO 3166-1 name, such as "United States".
+    pub async fn geo_top_tracks(
+        &self,
+        country: &str,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<Vec<ChartTrack>, errors::Error> {
+        require("country", country)?;
+
+        let mut params = vec![("country", country.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        let url = self.build_as_uri("geo.getTopTracks", &params);
+
+        Ok(self.get::<ChartTracks>(url).await?.into())
+    }
+
+    /// Fetch a page of the most popular artists in a country, ordered by
+    /// rank. The country is given by its ISO 3166-1 name, such as "United
+    /// States".
+    pub async fn geo_top_artists(
+        &self,
+        country: &str,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<Paged<ChartArtist>, errors::Error> {
+        require("country", country)?;
+
+        let mut params = vec![("country", country.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        let url = self.build_as_uri("geo.getTopArtists", &params);
+
+        Ok(self.get::<TopArtists>(url).await?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::mock_client;
+    use serde_json::json;
+    use wiremock::matchers::query_param;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn deserialize_chart_top_tracks() {
+        let json = json!({"tracks": {
+            "track": [{
+                "name": "Blinding Lights",
+                "duration": "0",
+                "playcount": "14342093",
+                "listeners": "1118824",
+                "mbid": "",
+                "url": "https://www.last.fm/music/The+Weeknd/_/Blinding+Lights",
+                "streamable": {"#text": "0", "fulltrack": "0"},
+                "artist": {"name": "The Weeknd", "mbid": "c8b03190-306c-4120-bb0b-6f2ebfc06ea9", "url": "https://www.last.fm/music/The+Weeknd"},
+                "image": []
+            }, {
+                "name": "Levitating",
+                "duration": "0",
+                "playcount": "6508736",
+                "listeners": "692215",
+                "artist": {"name": "Dua Lipa", "mbid": "", "url": "https://www.last.fm/music/Dua+Lipa"}
+            }],
+            "@attr": {"page": "2", "perPage": "2", "totalPages": "5000", "total": "10000"}
+        }});
+
+        let chart: ChartTracks = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            Vec::<ChartTrack>::from(chart),
+            vec![
+                ChartTrack {
+                    name: "Blinding Lights".into(),
+                    artist: Artist {
+                        name: "The Weeknd".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/The+Weeknd".into()),
+                    },
+                    playcount: 14342093,
+                    listeners: 1118824,
+                    rank: 3,
+                },
+                ChartTrack {
+                    name: "Levitating".into(),
+                    artist: Artist {
+                        name: "Dua Lipa".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Dua+Lipa".into()),
+                    },
+                    playcount: 6508736,
+                    listeners: 692215,
+                    rank: 4,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_chart_top_artists() {
+        let json = json!({"artists": {
+            "artist": [{
+                "name": "The Weeknd",
+                "playcount": "234196919",
+                "listeners": "2944638",
+                "mbid": "c8b03190-306c-4120-bb0b-6f2ebfc06ea9",
+                "url": "https://www.last.fm/music/The+Weeknd",
+                "streamable": "0",
+                "image": []
+            }, {
+                "name": "Dua Lipa",
+                "playcount": "98390078",
+                "listeners": "1923750",
+                "mbid": "",
+                "url": "https://www.last.fm/music/Dua+Lipa"
+            }],
+            "@attr": {"page": "1", "perPage": "2", "totalPages": "5000", "total": "10000"}
+        }});
+
+        let chart: ChartArtists = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            chart.artists.artists,
+            vec![
+                ChartArtist {
+                    name: "The Weeknd".into(),
+                    mbid: Some("c8b03190-306c-4120-bb0b-6f2ebfc06ea9".into()),
+                    playcount: 234196919,
+                    listeners: 2944638,
+                },
+                ChartArtist {
+                    name: "Dua Lipa".into(),
+                    mbid: None,
+                    playcount: 98390078,
+                    listeners: 1923750,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn geo_top_tracks_encodes_country() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "geo.getTopTracks"))
+            .and(query_param("country", "United States"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"tracks": {
+                "track": [{
+                    "name": "Blinding Lights",
+                    "duration": "200",
+                    "listeners": "1118824",
+                    "mbid": "",
+                    "url": "https://www.last.fm/music/The+Weeknd/_/Blinding+Lights",
+                    "artist": {"name": "The Weeknd", "mbid": "", "url": "https://www.last.fm/music/The+Weeknd"},
+                    "@attr": {"rank": "0"}
+                }],
+                "@attr": {"country": "United States", "page": "1", "perPage": "1", "totalPages": "50", "total": "50"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tracks = mock_client(&server)
+            .geo_top_tracks("United States", Some(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tracks,
+            vec![ChartTrack {
+                name: "Blinding Lights".into(),
+                artist: Artist {
+                    name: "The Weeknd".into(),
+                    images: Default::default(),
+                    url: Some("https://www.last.fm/music/The+Weeknd".into()),
+                },
+                playcount: 0,
+                listeners: 1118824,
+                rank: 1,
+            }]
+        );
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0]
+            .url
+            .query()
+            .unwrap()
+            .contains("country=United+States"));
+    }
+
+    #[test]
+    fn deserialize_geo_top_artists() {
+        let json = json!({"topartists": {
+            "artist": [{
+                "name": "Rosalía",
+                "listeners": "543210",
+                "mbid": "",
+                "url": "https://www.last.fm/music/Rosal%C3%ADa",
+                "streamable": "0",
+                "image": []
+            }, {
+                "name": "Bad Bunny",
+                "listeners": "412345",
+                "mbid": "89aa5ecb-59ad-46f5-b3eb-2d424e941f19",
+                "url": "https://www.last.fm/music/Bad+Bunny",
+                "streamable": "0",
+                "image": []
+            }],
+            "@attr": {"country": "Spain", "page": "3", "perPage": "2", "totalPages": "500", "total": "1000"}
+        }});
+
+        let top: TopArtists = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            Paged::from(top),
+            Paged {
+                items: vec![
+                    ChartArtist {
+                        name: "Rosalía".into(),
+                        mbid: None,
+                        playcount: 0,
+                        listeners: 543210,
+                    },
+                    ChartArtist {
+                        name: "Bad Bunny".into(),
+                        mbid: Some("89aa5ecb-59ad-46f5-b3eb-2d424e941f19".into()),
+                        playcount: 0,
+                        listeners: 412345,
+                    },
+                ],
+                pagination: Pagination {
+                    page: 3,
+                    per_page: 2,
+                    total_pages: 500,
+                    total: 1000,
+                },
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn geo_top_artists_encodes_country() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "geo.getTopArtists"))
+            .and(query_param("country", "United States"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"topartists": {
+                "artist": [],
+                "@attr": {"country": "United States", "page": "1", "perPage": "50", "totalPages": "0", "total": "0"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let artists = mock_client(&server)
+            .geo_top_artists("United States", None, None)
+            .await
+            .unwrap();
+
+        assert!(artists.items.is_empty());
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0]
+            .url
+            .query()
+            .unwrap()
+            .contains("country=United+States"));
+    }
+
+    #[tokio::test]
+    async fn geo_charts_reject_empty_country() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("api_key", "key"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = mock_client(&server);
+
+        let err = client.geo_top_tracks("", None, None).await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("country")));
+
+        let err = client.geo_top_artists(" ", None, None).await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("country")));
+    }
+}
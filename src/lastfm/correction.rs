@@ -0,0 +1,364 @@
+use serde::de::{Error as _, IgnoredAny, Unexpected};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+
+use super::{de, errors, require, Client, HttpClient};
+
+/// The canonical spelling of an artist or track, as returned by the
+/// `getCorrection` methods.
+#[derive(Debug, PartialEq)]
+pub struct Correction {
+    pub artist: String,
+    /// The corrected track name, for track corrections
+    pub track: Option<String>,
+    /// The MusicBrainz ID of the corrected artist or track
+    pub mbid: Option<String>,
+}
+
+/// Data fetched with autocorrection enabled, along with the names Last.fm
+/// substituted for those that were given.
+///
+/// Each name is `None` unless Last.fm corrected it.
+#[derive(Debug, PartialEq)]
+pub struct Corrected<T> {
+    pub data: T,
+    pub artist: Option<String>,
+    pub track: Option<String>,
+}
+
+impl<T> Corrected<T> {
+    pub(crate) fn new(data: T) -> Self {
+        Corrected {
+            data,
+            artist: None,
+            track: None,
+        }
+    }
+
+    /// Record `corrected` as the artist, unless it is the `given` name.
+    pub(crate) fn artist(mut self, given: &str, corrected: Option<String>) -> Self {
+        self.artist = corrected.filter(|c| c != given);
+        self
+    }
+
+    /// Record `corrected` as the track, unless it is the `given` name.
+    pub(crate) fn track(mut self, given: &str, corrected: Option<String>) -> Self {
+        self.track = corrected.filter(|c| c != given);
+        self
+    }
+
+    /// Returns true if Last.fm corrected any of the given names
+    pub fn is_corrected(&self) -> bool {
+        self.artist.is_some() || self.track.is_some()
+    }
+}
+
+/// Last.fm answers with `{"corrections": "\n"}` rather than an empty object
+/// when it has no correction.
+#[derive(Deserialize, Debug)]
+struct Corrections<T> {
+    corrections: MaybeCorrection<T>,
+}
+
+/// A correction, or one of the shapes Last.fm uses for none. Anything else,
+/// such as a correction missing its name, fails to decode.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum MaybeCorrection<T> {
+    Some { correction: T },
+    Blank(#[serde(deserialize_with = "blank")] ()),
+    Empty(#[serde(deserialize_with = "empty_object")] ()),
+}
+
+/// Accept only a string of whitespace, such as `"\n"`.
+fn blank<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(), D::Error> {
+    let s = String::deserialize(deserializer)?;
+    if !s.trim().is_empty() {
+        return Err(D::Error::invalid_value(
+            Unexpected::Str(&s),
+            &"a blank string",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Accept only an empty object, `{}`.
+fn empty_object<'de, D: Deserializer<'de>>(deserializer: D) -> Result<(), D::Error> {
+    let map = HashMap::<String, IgnoredAny>::deserialize(deserializer)?;
+    if !map.is_empty() {
+        return Err(D::Error::invalid_length(map.len(), &"an empty object"));
+    }
+
+    Ok(())
+}
+
+impl<T> MaybeCorrection<T> {
+    fn into_option(self) -> Option<T> {
+        match self {
+            MaybeCorrection::Some { correction } => Some(correction),
+            MaybeCorrection::Blank(()) | MaybeCorrection::Empty(()) => None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct CorrectedEntity {
+    name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    mbid: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TrackCorrection {
+    track: CorrectedTrack,
+}
+
+#[derive(Deserialize, Debug)]
+struct CorrectedTrack {
+    name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    mbid: Option<String>,
+    artist: CorrectedEntity,
+}
+
+#[derive(Deserialize, Debug)]
+struct ArtistCorrection {
+    artist: CorrectedEntity,
+}
+
+impl From<TrackCorrection> for Correction {
+    fn from(c: TrackCorrection) -> Self {
+        Correction {
+            artist: c.track.artist.name,
+            track: Some(c.track.name),
+            mbid: c.track.mbid,
+        }
+    }
+}
+
+impl From<ArtistCorrection> for Correction {
+    fn from(c: ArtistCorrection) -> Self {
+        Correction {
+            artist: c.artist.name,
+            track: None,
+            mbid: c.artist.mbid,
+        }
+    }
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Look up the canonical spelling of a track, returning `None` if Last.fm
+    /// has no correction for it.
+    pub async fn track_correction(
+        &self,
+        artist: &str,
+        track: &str,
+    ) -> Result<Option<Correction>, errors::Error> {
+        require("artist", artist)?;
+        require("track", track)?;
+
+        let url = self.build_as_uri(
+            "track.getCorrection",
+            &[("artist", artist), ("track", track)],
+        );
+        let corrections = self.get::<Corrections<TrackCorrection>>(url).await?;
+
+        Ok(corrections.corrections.into_option().map(Correction::from))
+    }
+
+    /// Look up the canonical spelling of an artist, returning `None` if
+    /// Last.fm has no correction for it.
+    pub async fn artist_correction(
+        &self,
+        artist: &str,
+    ) -> Result<Option<Correction>, errors::Error> {
+        require("artist", artist)?;
+
+        let url = self.build_as_uri("artist.getCorrection", &[("artist", artist)]);
+        let corrections = self.get::<Corrections<ArtistCorrection>>(url).await?;
+
+        Ok(corrections.corrections.into_option().map(Correction::from))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::mock_client;
+    use serde_json::json;
+    use wiremock::matchers::{method, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn corrected_only_records_changed_names() {
+        let corrected = Corrected::new(())
+            .artist("chr", Some("Cher".into()))
+            .track("Believe", Some("Believe".into()));
+        assert_eq!(corrected.artist.as_deref(), Some("Cher"));
+        assert_eq!(corrected.track, None);
+        assert!(corrected.is_corrected());
+
+        let uncorrected = Corrected::new(())
+            .artist("Cher", None)
+            .track("Believe", None);
+        assert_eq!(uncorrected, Corrected::new(()));
+        assert!(!uncorrected.is_corrected());
+    }
+
+    #[tokio::test]
+    async fn track_correction_found() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "track.getCorrection"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"corrections": {
+                    "correction": {
+                        "track": {
+                            "name": "Mr. Brightside",
+                            "mbid": "",
+                            "url": "https://www.last.fm/music/The+Killers/_/Mr.+Brightside",
+                            "artist": {
+                                "name": "The Killers",
+                                "mbid": "95e1ead9-4d31-4808-a7ac-32c3614c116b",
+                                "url": "https://www.last.fm/music/The+Killers"
+                            }
+                        },
+                        "@attr": {"index": "0", "artistcorrected": "1", "trackcorrected": "1"}
+                    }
+                }})),
+            )
+            .mount(&server)
+            .await;
+
+        let correction = mock_client(&server)
+            .track_correction("the killers", "mr brightside")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            correction,
+            Some(Correction {
+                artist: "The Killers".into(),
+                track: Some("Mr. Brightside".into()),
+                mbid: None,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn track_correction_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "track.getCorrection"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"corrections": "\n"})))
+            .mount(&server)
+            .await;
+
+        let correction = mock_client(&server)
+            .track_correction("Nobody", "Nothing")
+            .await
+            .unwrap();
+
+        assert_eq!(correction, None);
+    }
+
+    #[tokio::test]
+    async fn artist_correction_found() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "artist.getCorrection"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"corrections": {
+                    "correction": {
+                        "artist": {
+                            "name": "Guns N' Roses",
+                            "mbid": "eeb1195b-f213-4ce1-b28c-8565211f8e43",
+                            "url": "https://www.last.fm/music/Guns+N%27+Roses"
+                        },
+                        "@attr": {"index": "0"}
+                    }
+                }})),
+            )
+            .mount(&server)
+            .await;
+
+        let correction = mock_client(&server)
+            .artist_correction("guns and roses")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            correction,
+            Some(Correction {
+                artist: "Guns N' Roses".into(),
+                track: None,
+                mbid: Some("eeb1195b-f213-4ce1-b28c-8565211f8e43".into()),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn artist_correction_not_found() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "artist.getCorrection"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"corrections": {}})))
+            .mount(&server)
+            .await;
+
+        let correction = mock_client(&server)
+            .artist_correction("Cher")
+            .await
+            .unwrap();
+
+        assert_eq!(correction, None);
+    }
+
+    #[tokio::test]
+    async fn malformed_correction_is_a_decode_error() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "artist.getCorrection"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"corrections": {
+                    "correction": {
+                        "artist": {"mbid": "", "url": "https://www.last.fm/music/Cher"},
+                        "@attr": {"index": "0"}
+                    }
+                }})),
+            )
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server)
+            .artist_correction("cher")
+            .await
+            .unwrap_err();
+
+        assert!(err.is_decode(), "{:?}", err);
+    }
+
+    #[test]
+    fn only_blank_shapes_are_no_correction() {
+        let parse = |value| serde_json::from_value::<Corrections<ArtistCorrection>>(value);
+
+        assert!(parse(json!({"corrections": "\n"})).is_ok());
+        assert!(parse(json!({"corrections": {}})).is_ok());
+        assert!(parse(json!({"corrections": "Cher"})).is_err());
+        assert!(parse(json!({"corrections": {"unexpected": 1}})).is_err());
+        assert!(parse(json!({"corrections": []})).is_err());
+    }
+
+    #[tokio::test]
+    async fn corrections_reject_empty_input() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = mock_client(&server);
+
+        let err = client.track_correction("Cher", "").await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("track")));
+
+        let err = client.artist_correction(" ").await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("artist")));
+    }
+}
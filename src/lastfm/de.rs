@@ -0,0 +1,382 @@
+//! Helpers for deserializing the quirks of Last.fm's JSON responses.
+
+use serde::de::{self, Deserialize, Deserializer};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// A number which Last.fm encodes either as a JSON number or as a string, such
+/// as a playcount of `"670120"`.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum NumberOrString<T> {
+    Number(T),
+    String(String),
+}
+
+impl<T> NumberOrString<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    fn parse<E: de::Error>(self) -> Result<T, E> {
+        match self {
+            NumberOrString::Number(n) => Ok(n),
+            NumberOrString::String(s) => s.parse().map_err(de::Error::custom),
+        }
+    }
+}
+
+/// Deserialize a number which may be encoded as either a JSON number or a
+/// string, such as `670120` or `"670120"`.
+pub(crate) fn number<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    NumberOrString::deserialize(deserializer)?.parse()
+}
+
+/// Like [`number`], but treats a missing value or empty string as `None`.
+pub(crate) fn option_number<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    match Option::<NumberOrString<T>>::deserialize(deserializer)? {
+        Some(NumberOrString::String(s)) if s.is_empty() => Ok(None),
+        Some(n) => n.parse().map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Deserialize an optional string, treating a missing value or empty string
+/// as `None`.
+pub(crate) fn option_from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(s) if !s.is_empty() => s.parse().map(Some).map_err(de::Error::custom),
+        _ => Ok(None),
+    }
+}
+
+/// Deserialize a list which Last.fm collapses into a bare object when it has
+/// exactly one element.
+pub(crate) fn one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        Many(Vec<T>),
+        One(T),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(v) => v,
+        OneOrMany::One(t) => vec![t],
+    })
+}
+
+/// Deserialize the text out of a `{"#text": ...}` object, as used for names
+/// which carry extra attributes such as an mbid.
+pub(crate) fn text<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    struct Text {
+        #[serde(rename = "#text")]
+        text: String,
+    }
+
+    Ok(Text::deserialize(deserializer)?.text)
+}
+
+/// Deserialize an artist name, which Last.fm gives either as a bare string,
+/// under `#text`, or under `name` in the nested artist objects of extended
+/// and tag responses.
+pub(crate) fn artist_name<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum ArtistName {
+        Bare(String),
+        Text {
+            #[serde(rename = "#text")]
+            text: String,
+        },
+        Named {
+            name: String,
+        },
+    }
+
+    Ok(match ArtistName::deserialize(deserializer)? {
+        ArtistName::Bare(name) | ArtistName::Text { text: name } | ArtistName::Named { name } => {
+            name
+        }
+    })
+}
+
+/// Like [`text`], but treats a missing object or empty text as `None`.
+pub(crate) fn option_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    struct Text {
+        #[serde(rename = "#text")]
+        text: String,
+    }
+
+    Ok(Option::<Text>::deserialize(deserializer)?
+        .map(|t| t.text)
+        .filter(|t| !t.is_empty()))
+}
+
+/// A Unix timestamp in one of the shapes Last.fm uses: a bare string or
+/// number, or an object with the string under `uts` (scrobble dates) or
+/// `unixtime` (registration dates) alongside a formatted `#text`.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Number(u64),
+    Bare(String),
+    Uts { uts: String },
+    Unixtime { unixtime: String },
+}
+
+impl Timestamp {
+    fn seconds<E: de::Error>(self) -> Result<u64, E> {
+        match self {
+            Timestamp::Number(n) => Ok(n),
+            Timestamp::Bare(s)
+            | Timestamp::Uts { uts: s }
+            | Timestamp::Unixtime { unixtime: s } => s.parse().map_err(de::Error::custom),
+        }
+    }
+}
+
+/// Deserialize a timestamp, such as `{"uts": "1613463960", "#text": ...}` or
+/// `"1613463960"`, into seconds since the Unix epoch.
+pub(crate) fn timestamp<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Timestamp::deserialize(deserializer)?.seconds()
+}
+
+/// Like [`timestamp`], but treats a missing value as `None`.
+pub(crate) fn option_timestamp<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<Timestamp>::deserialize(deserializer)?
+        .map(Timestamp::seconds)
+        .transpose()
+}
+
+/// Deserialize the rank out of an `{"@attr": {"rank": "1"}}` attribute.
+pub(crate) fn rank<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    struct Attr {
+        #[serde(deserialize_with = "number")]
+        rank: u32,
+    }
+
+    Ok(Attr::deserialize(deserializer)?.rank)
+}
+
+/// Deserialize an `[{"#text": ..., "size": ...}]` list of image URLs by size.
+pub(crate) fn images<'de, D>(deserializer: D) -> Result<super::Images, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    struct Image {
+        #[serde(rename = "#text")]
+        url: String,
+        #[serde(default)]
+        size: String,
+    }
+
+    let mut images = super::Images::default();
+    for image in one_or_many::<_, Image>(deserializer)? {
+        let slot = match image.size.as_str() {
+            "small" => &mut images.small,
+            "medium" => &mut images.medium,
+            "large" => &mut images.large,
+            "extralarge" => &mut images.extralarge,
+            "mega" => &mut images.mega,
+            _ => continue,
+        };
+        *slot = Some(image.url).filter(|url| !url.is_empty());
+    }
+
+    Ok(images)
+}
+
+/// Deserialize a `{"tag": [{"name": ...}]}` list into the tag names.
+pub(crate) fn tag_names<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    struct Tags {
+        #[serde(default, deserialize_with = "one_or_many")]
+        tag: Vec<TagName>,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct TagName {
+        name: String,
+    }
+
+    let tags = Tags::deserialize(deserializer)?;
+
+    Ok(tags.tag.into_iter().map(|t| t.name).collect())
+}
+
+/// Deserialize the summary out of a `{"summary": ..., "content": ...}` wiki
+/// or biography.
+pub(crate) fn wiki_summary<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    struct Wiki {
+        summary: String,
+    }
+
+    Ok(Option::<Wiki>::deserialize(deserializer)?.map(|w| w.summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    struct List {
+        #[serde(default, deserialize_with = "one_or_many")]
+        item: Vec<u32>,
+    }
+
+    #[test]
+    fn timestamp_accepts_each_shape() {
+        #[derive(serde::Deserialize)]
+        struct Item {
+            #[serde(deserialize_with = "timestamp")]
+            date: u64,
+        }
+
+        for date in &[
+            json!({"uts": "1613463960", "#text": "16 Feb 2021, 08:26"}),
+            json!({"unixtime": "1613463960", "#text": 1613463960}),
+            json!("1613463960"),
+            json!(1613463960),
+        ] {
+            let item: Item = serde_json::from_value(json!({ "date": date })).unwrap();
+            assert_eq!(item.date, 1613463960, "{}", date);
+        }
+
+        let invalid = serde_json::from_value::<Item>(json!({"date": {"uts": "soon"}}));
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn artist_name_accepts_each_shape() {
+        #[derive(serde::Deserialize)]
+        struct Item {
+            #[serde(deserialize_with = "artist_name")]
+            artist: String,
+        }
+
+        for artist in &[
+            json!("Cher"),
+            json!({"#text": "Cher", "mbid": ""}),
+            json!({"name": "Cher", "url": "https://www.last.fm/music/Cher"}),
+        ] {
+            let item: Item = serde_json::from_value(json!({ "artist": artist })).unwrap();
+            assert_eq!(item.artist, "Cher", "{}", artist);
+        }
+    }
+
+    #[test]
+    fn images_by_size() {
+        #[derive(serde::Deserialize)]
+        struct Item {
+            #[serde(deserialize_with = "images")]
+            image: crate::lastfm::Images,
+        }
+
+        let item: Item = serde_json::from_value(json!({"image": [
+            {"#text": "https://lastfm.freetls.fastly.net/i/u/34s/a.png", "size": "small"},
+            {"#text": "https://lastfm.freetls.fastly.net/i/u/64s/a.png", "size": "medium"},
+            {"#text": "", "size": "large"},
+            {"#text": "https://lastfm.freetls.fastly.net/i/u/300x300/a.png", "size": "extralarge"},
+            {"#text": "https://lastfm.freetls.fastly.net/i/u/a.png", "size": ""}
+        ]}))
+        .unwrap();
+
+        assert_eq!(
+            item.image,
+            crate::lastfm::Images {
+                small: Some("https://lastfm.freetls.fastly.net/i/u/34s/a.png".into()),
+                medium: Some("https://lastfm.freetls.fastly.net/i/u/64s/a.png".into()),
+                large: None,
+                extralarge: Some("https://lastfm.freetls.fastly.net/i/u/300x300/a.png".into()),
+                mega: None,
+            }
+        );
+    }
+
+    #[test]
+    fn number_accepts_strings_and_numbers() {
+        #[derive(serde::Deserialize)]
+        struct Item {
+            #[serde(deserialize_with = "number")]
+            playcount: u64,
+            #[serde(default, deserialize_with = "option_number")]
+            duration: Option<u64>,
+        }
+
+        for playcount in &[json!("42"), json!(42)] {
+            let item: Item = serde_json::from_value(json!({ "playcount": playcount })).unwrap();
+            assert_eq!(item.playcount, 42, "{}", playcount);
+            assert_eq!(item.duration, None);
+        }
+
+        for duration in &[json!("42"), json!(42)] {
+            let item: Item =
+                serde_json::from_value(json!({"playcount": 1, "duration": duration})).unwrap();
+            assert_eq!(item.duration, Some(42), "{}", duration);
+        }
+
+        let empty: Item = serde_json::from_value(json!({"playcount": 1, "duration": ""})).unwrap();
+        assert_eq!(empty.duration, None);
+
+        assert!(serde_json::from_value::<Item>(json!({"playcount": "many"})).is_err());
+    }
+
+    #[test]
+    fn one_or_many_accepts_both_shapes() {
+        let many: List = serde_json::from_value(json!({"item": [1, 2]})).unwrap();
+        assert_eq!(many.item, vec![1, 2]);
+
+        let one: List = serde_json::from_value(json!({"item": 1})).unwrap();
+        assert_eq!(one.item, vec![1]);
+    }
+}
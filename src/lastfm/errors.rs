@@ -5,138 +5,306 @@ use std::convert::From;
 use std::convert::TryFrom;
 use std::fmt;
 
-#[derive(Debug)]
 pub enum Error {
-    /// An error occurred while parsing the response
-    ParsingError(serde_json::error::Error),
-    /// An error occurred during the request
-    HTTPError(reqwest::Error),
-    /// An error occurred from the API
-    APIError(ErrorResponse),
+    /// The request could not be sent, or its response could not be received,
+    /// such as when the connection fails or times out
+    Transport(reqwest::Error),
+    /// The response body could not be decoded, such as when it is not valid
+    /// JSON or does not match the expected shape
+    Decode(Box<dyn std::error::Error + Send + Sync>),
+    /// The API responded with an error payload
+    Api(ErrorResponse),
+    /// An authenticated method was called without the named credential
+    /// configured
+    MissingCredentials(&'static str),
+    /// The server responded with an unsuccessful status, along with the
+    /// response body if it was short enough to be useful
+    HttpStatus(reqwest::StatusCode, Option<String>),
+    /// A required parameter was empty, so no request was made
+    InvalidInput(String),
+    /// The last.fm website responded with the given content type rather than
+    /// JSON, such as an HTML login page
+    UnexpectedContentType(String),
+}
+
+impl Error {
+    /// Returns true if the request timed out
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, Error::Transport(e) if e.is_timeout())
+    }
+
+    /// Returns true if the connection to the server could not be established
+    pub fn is_connect(&self) -> bool {
+        matches!(self, Error::Transport(e) if e.is_connect())
+    }
+
+    /// Returns true if the response body could not be decoded
+    pub fn is_decode(&self) -> bool {
+        matches!(self, Error::Decode(_))
+    }
+
+    /// The error code sent by the API, if this is an API error
+    pub fn api_code(&self) -> Option<&ErrorCode> {
+        match self {
+            Error::Api(e) => Some(&e.error),
+            _ => None,
+        }
+    }
 }
 
 impl From<serde_json::Error> for Error {
     fn from(error: serde_json::Error) -> Self {
-        Error::ParsingError(error)
+        Error::Decode(Box::new(error))
     }
 }
 
+/// Body decoding failures, such as a corrupt gzip stream, are reported as
+/// [`Error::Decode`]. All other failures are [`Error::Transport`].
+///
+/// The API and session keys are masked in the URL of the failed request, so
+/// that neither the error nor its source can leak them.
 impl From<reqwest::Error> for Error {
-    fn from(error: reqwest::Error) -> Self {
-        Error::HTTPError(error)
+    fn from(mut error: reqwest::Error) -> Self {
+        if let Some(url) = error.url_mut() {
+            *url = super::http::redact_url(url);
+        }
+
+        if error.is_decode() {
+            Error::Decode(Box::new(error))
+        } else {
+            Error::Transport(error)
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Transport(e) => f.debug_tuple("Transport").field(e).finish(),
+            Error::Decode(e) => f.debug_tuple("Decode").field(e).finish(),
+            Error::Api(e) => f.debug_tuple("Api").field(e).finish(),
+            Error::MissingCredentials(c) => f.debug_tuple("MissingCredentials").field(c).finish(),
+            Error::HttpStatus(status, body) => f
+                .debug_tuple("HttpStatus")
+                .field(status)
+                .field(body)
+                .finish(),
+            Error::InvalidInput(m) => f.debug_tuple("InvalidInput").field(m).finish(),
+            Error::UnexpectedContentType(c) => {
+                f.debug_tuple("UnexpectedContentType").field(c).finish()
+            }
+        }
     }
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Error::ParsingError(e) => write!(f, "parse error: {}", e),
-            Error::HTTPError(e) => write!(f, "request error: {}", e),
-            Error::APIError(e) => write!(f, "LastFM error ({}): {}", e.error, e.message),
+            Error::Transport(e) => write!(f, "request error: {}", e),
+            Error::Decode(e) => write!(f, "decode error: {}", e),
+            Error::Api(e) => write!(f, "LastFM error ({}): {}", e.error.code(), e.message),
+            Error::MissingCredentials(c) => write!(f, "missing credentials: no {} configured", c),
+            Error::HttpStatus(status, Some(body)) => write!(f, "HTTP status {}: {}", status, body),
+            Error::HttpStatus(status, None) => write!(f, "HTTP status {}", status),
+            Error::InvalidInput(m) => write!(f, "invalid input: {}", m),
+            Error::UnexpectedContentType(c) => write!(
+                f,
+                "expected JSON but got {}; the endpoint may require authentication",
+                c
+            ),
         }
     }
 }
 
 impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e),
+            Error::Decode(e) => Some(e.as_ref()),
+            Error::Api(_)
+            | Error::MissingCredentials(_)
+            | Error::HttpStatus(..)
+            | Error::InvalidInput(_)
+            | Error::UnexpectedContentType(_) => None,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ErrorCode {
     /// Invalid service -This service does not exist
-    InvalidService = 2,
+    InvalidService,
     /// Invalid Method - No method with that name in this package
-    InvalidMethod = 3,
+    InvalidMethod,
     /// Authentication Failed - You do not have permissions to access the service
-    AuthenticationFailed = 4,
+    AuthenticationFailed,
     /// Invalid format - This service doesn't exist in that format
-    InvalidFormat = 5,
+    InvalidFormat,
     /// Invalid parameters - Your request is missing a required parameter
-    InvalidParameters = 6,
+    InvalidParameters,
     /// Invalid resource specified
-    InvalidResource = 7,
+    InvalidResource,
     /// Operation failed - Most likely the backend service failed. Please try again.
-    OperationFailed = 8,
+    OperationFailed,
     /// Invalid session key - Please re-authenticate
-    InvalidSessionKey = 9,
+    InvalidSessionKey,
     /// Invalid API key - You must be granted a valid key by last.fm
-    InvalidAPIKey = 10,
+    InvalidAPIKey,
     /// Service Offline - This service is temporarily offline. Try again later.
-    ServiceOffline = 11,
+    ServiceOffline,
     /// Subscribers Only - This station is only available to paid last.fm subscribers
-    SubscribersOnly = 12,
+    SubscribersOnly,
     /// Invalid method signature supplied
-    InvalidMethodSignature = 13,
+    InvalidMethodSignature,
     /// Unauthorized Token - This token has not been authorized
-    UnauthorizedToken = 14,
+    UnauthorizedToken,
     /// This item is not available for streaming.
-    StreamingNotAvailable = 15,
+    StreamingNotAvailable,
     /// The service is temporarily unavailable, please try again.
-    ServiceTemporarilyUnavailable = 16,
+    ServiceTemporarilyUnavailable,
     /// Login: User requires to be logged in
-    RequiresLogin = 17,
+    RequiresLogin,
     /// Trial Expired - This user has no free radio plays left. Subscription required.
-    TrialExpired = 18,
+    TrialExpired,
     /// Not Enough Content - There is not enough content to play this station
-    NotEnoughContent = 20,
+    NotEnoughContent,
     /// Not Enough Members - This group does not have enough members for radio
-    NotEnoughMembers = 21,
+    NotEnoughMembers,
     /// Not Enough Fans - This artist does not have enough fans for for radio
-    NotEnoughFans = 22,
+    NotEnoughFans,
     /// Not Enough Neighbours - There are not enough neighbours for radio
-    NotEnoughNeighbours = 23,
+    NotEnoughNeighbours,
     /// No Peak Radio - This user is not allowed to listen to radio during peak usage
-    NoPeakRadio = 24,
+    NoPeakRadio,
     /// Radio Not Found - Radio station not found
-    RadioNotFound = 25,
+    RadioNotFound,
     /// API Key Suspended - This application is not allowed to make requests to the web services
-    APIKeySuspended = 26,
+    APIKeySuspended,
     /// Deprecated - This type of request is no longer supported
-    Deprecated = 27,
+    Deprecated,
     /// Rate Limit Exceded - Your IP has made too many requests in a short period, exceeding our API guidelines
-    RateLimitExceeded = 29,
+    RateLimitExceeded,
+    /// An error code not known to this library
+    Unknown(u64),
+}
+
+impl ErrorCode {
+    /// The numeric code sent by the API
+    pub fn code(&self) -> u64 {
+        match self {
+            ErrorCode::InvalidService => 2,
+            ErrorCode::InvalidMethod => 3,
+            ErrorCode::AuthenticationFailed => 4,
+            ErrorCode::InvalidFormat => 5,
+            ErrorCode::InvalidParameters => 6,
+            ErrorCode::InvalidResource => 7,
+            ErrorCode::OperationFailed => 8,
+            ErrorCode::InvalidSessionKey => 9,
+            ErrorCode::InvalidAPIKey => 10,
+            ErrorCode::ServiceOffline => 11,
+            ErrorCode::SubscribersOnly => 12,
+            ErrorCode::InvalidMethodSignature => 13,
+            ErrorCode::UnauthorizedToken => 14,
+            ErrorCode::StreamingNotAvailable => 15,
+            ErrorCode::ServiceTemporarilyUnavailable => 16,
+            ErrorCode::RequiresLogin => 17,
+            ErrorCode::TrialExpired => 18,
+            ErrorCode::NotEnoughContent => 20,
+            ErrorCode::NotEnoughMembers => 21,
+            ErrorCode::NotEnoughFans => 22,
+            ErrorCode::NotEnoughNeighbours => 23,
+            ErrorCode::NoPeakRadio => 24,
+            ErrorCode::RadioNotFound => 25,
+            ErrorCode::APIKeySuspended => 26,
+            ErrorCode::Deprecated => 27,
+            ErrorCode::RateLimitExceeded => 29,
+            ErrorCode::Unknown(u) => *u,
+        }
+    }
+
+    /// The error for a numeric code sent by the API, which is
+    /// [`ErrorCode::Unknown`] if the code is not known to this library.
+    pub fn from_code(u: u64) -> Self {
+        match u {
+            2 => ErrorCode::InvalidService,
+            3 => ErrorCode::InvalidMethod,
+            4 => ErrorCode::AuthenticationFailed,
+            5 => ErrorCode::InvalidFormat,
+            6 => ErrorCode::InvalidParameters,
+            7 => ErrorCode::InvalidResource,
+            8 => ErrorCode::OperationFailed,
+            9 => ErrorCode::InvalidSessionKey,
+            10 => ErrorCode::InvalidAPIKey,
+            11 => ErrorCode::ServiceOffline,
+            12 => ErrorCode::SubscribersOnly,
+            13 => ErrorCode::InvalidMethodSignature,
+            14 => ErrorCode::UnauthorizedToken,
+            15 => ErrorCode::StreamingNotAvailable,
+            16 => ErrorCode::ServiceTemporarilyUnavailable,
+            17 => ErrorCode::RequiresLogin,
+            18 => ErrorCode::TrialExpired,
+            20 => ErrorCode::NotEnoughContent,
+            21 => ErrorCode::NotEnoughMembers,
+            22 => ErrorCode::NotEnoughFans,
+            23 => ErrorCode::NotEnoughNeighbours,
+            24 => ErrorCode::NoPeakRadio,
+            25 => ErrorCode::RadioNotFound,
+            26 => ErrorCode::APIKeySuspended,
+            27 => ErrorCode::Deprecated,
+            29 => ErrorCode::RateLimitExceeded,
+            _ => ErrorCode::Unknown(u),
+        }
+    }
 }
 
 impl std::fmt::Display for ErrorCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self)
+        let description = match self {
+            ErrorCode::Unknown(u) => return write!(f, "Unknown error code {}", u),
+            ErrorCode::InvalidService => "Invalid service - This service does not exist",
+            ErrorCode::InvalidMethod => "Invalid Method - No method with that name in this package",
+            ErrorCode::AuthenticationFailed => "Authentication Failed - You do not have permissions to access the service",
+            ErrorCode::InvalidFormat => "Invalid format - This service doesn't exist in that format",
+            ErrorCode::InvalidParameters => "Invalid parameters - Your request is missing a required parameter",
+            ErrorCode::InvalidResource => "Invalid resource specified",
+            ErrorCode::OperationFailed => "Operation failed - Most likely the backend service failed. Please try again.",
+            ErrorCode::InvalidSessionKey => "Invalid session key - Please re-authenticate",
+            ErrorCode::InvalidAPIKey => "Invalid API key - You must be granted a valid key by last.fm",
+            ErrorCode::ServiceOffline => "Service Offline - This service is temporarily offline. Try again later.",
+            ErrorCode::SubscribersOnly => "Subscribers Only - This station is only available to paid last.fm subscribers",
+            ErrorCode::InvalidMethodSignature => "Invalid method signature supplied",
+            ErrorCode::UnauthorizedToken => "Unauthorized Token - This token has not been authorized",
+            ErrorCode::StreamingNotAvailable => "This item is not available for streaming.",
+            ErrorCode::ServiceTemporarilyUnavailable => "The service is temporarily unavailable, please try again.",
+            ErrorCode::RequiresLogin => "Login: User requires to be logged in",
+            ErrorCode::TrialExpired => "Trial Expired - This user has no free radio plays left. Subscription required.",
+            ErrorCode::NotEnoughContent => "Not Enough Content - There is not enough content to play this station",
+            ErrorCode::NotEnoughMembers => "Not Enough Members - This group does not have enough members for radio",
+            ErrorCode::NotEnoughFans => "Not Enough Fans - This artist does not have enough fans for radio",
+            ErrorCode::NotEnoughNeighbours => "Not Enough Neighbours - There are not enough neighbours for radio",
+            ErrorCode::NoPeakRadio => "No Peak Radio - This user is not allowed to listen to radio during peak usage",
+            ErrorCode::RadioNotFound => "Radio Not Found - Radio station not found",
+            ErrorCode::APIKeySuspended => "API Key Suspended - This application is not allowed to make requests to the web services",
+            ErrorCode::Deprecated => "Deprecated - This type of request is no longer supported",
+            ErrorCode::RateLimitExceeded => "Rate Limit Exceeded - Your IP has made too many requests in a short period, exceeding our API guidelines",
+        };
+
+        f.write_str(description)
     }
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
 pub struct InvalidErrorCode(u64);
 
+/// Fails for codes which are not known to this library. Use
+/// [`ErrorCode::from_code`] to map them to [`ErrorCode::Unknown`] instead.
 impl TryFrom<u64> for ErrorCode {
     type Error = InvalidErrorCode;
 
     fn try_from(u: u64) -> Result<Self, Self::Error> {
-        match u {
-            2 => Ok(ErrorCode::InvalidService),
-            3 => Ok(ErrorCode::InvalidMethod),
-            4 => Ok(ErrorCode::AuthenticationFailed),
-            5 => Ok(ErrorCode::InvalidFormat),
-            6 => Ok(ErrorCode::InvalidParameters),
-            7 => Ok(ErrorCode::InvalidResource),
-            8 => Ok(ErrorCode::OperationFailed),
-            9 => Ok(ErrorCode::InvalidSessionKey),
-            10 => Ok(ErrorCode::InvalidAPIKey),
-            11 => Ok(ErrorCode::ServiceOffline),
-            12 => Ok(ErrorCode::SubscribersOnly),
-            13 => Ok(ErrorCode::InvalidMethodSignature),
-            14 => Ok(ErrorCode::UnauthorizedToken),
-            15 => Ok(ErrorCode::StreamingNotAvailable),
-            16 => Ok(ErrorCode::ServiceTemporarilyUnavailable),
-            17 => Ok(ErrorCode::RequiresLogin),
-            18 => Ok(ErrorCode::TrialExpired),
-            20 => Ok(ErrorCode::NotEnoughContent),
-            21 => Ok(ErrorCode::NotEnoughMembers),
-            22 => Ok(ErrorCode::NotEnoughFans),
-            23 => Ok(ErrorCode::NotEnoughNeighbours),
-            24 => Ok(ErrorCode::NoPeakRadio),
-            25 => Ok(ErrorCode::RadioNotFound),
-            26 => Ok(ErrorCode::APIKeySuspended),
-            27 => Ok(ErrorCode::Deprecated),
-            29 => Ok(ErrorCode::RateLimitExceeded),
-            _ => Err(InvalidErrorCode(u as u64)),
+        match ErrorCode::from_code(u) {
+            ErrorCode::Unknown(u) => Err(InvalidErrorCode(u)),
+            code => Ok(code),
         }
     }
 }
@@ -158,38 +326,35 @@ impl<'de> Visitor<'de> for ErrorCodeVisitor {
     type Value = ErrorCode;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("an integer between 2 and 29")
+        formatter.write_str("an integer error code")
     }
 
     fn visit_u8<E>(self, value: u8) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        ErrorCode::try_from(value as u64)
-            .map_err(|e| E::custom(format!("invalid error code: {}", e.0)))
+        Ok(ErrorCode::from_code(value as u64))
     }
 
     fn visit_u32<E>(self, value: u32) -> Result<ErrorCode, E>
     where
         E: de::Error,
     {
-        ErrorCode::try_from(value as u64)
-            .map_err(|e| E::custom(format!("invalid error code: {}", e.0)))
+        Ok(ErrorCode::from_code(value as u64))
     }
 
     fn visit_u64<E>(self, value: u64) -> Result<ErrorCode, E>
     where
         E: de::Error,
     {
-        ErrorCode::try_from(value as u64)
-            .map_err(|e| E::custom(format!("invalid error code: {}", e.0)))
+        Ok(ErrorCode::from_code(value))
     }
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
 pub struct ErrorResponse {
-    error: ErrorCode,
-    message: String,
+    pub error: ErrorCode,
+    pub message: String,
 }
 
 #[cfg(test)]
@@ -223,4 +388,136 @@ mod tests {
         );
         assert_eq!(Ok(ErrorCode::InvalidFormat), ErrorCode::try_from(5));
     }
+
+    #[test]
+    fn error_code_from_code() {
+        assert_eq!(ErrorCode::Unknown(255), ErrorCode::from_code(255));
+        assert_eq!(
+            ErrorCode::Unknown(4294967295),
+            ErrorCode::from_code(4294967295)
+        );
+        assert_eq!(ErrorCode::InvalidFormat, ErrorCode::from_code(5));
+    }
+
+    #[tokio::test]
+    async fn from_reqwest_connect_error() {
+        // Reserve a free port and release it so nothing is listening on it
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let res = reqwest::get(&format!("http://{}", addr)).await;
+
+        let err = Error::from(res.unwrap_err());
+        assert!(matches!(err, Error::Transport(_)));
+        assert!(err.is_connect());
+        assert!(!err.is_timeout());
+        assert!(!err.is_decode());
+    }
+
+    #[tokio::test]
+    async fn http_error_masks_api_key() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let url = format!("http://{}/2.0?method=track.getsimilar&api_key=s3cr3t", addr);
+        let err = Error::from(reqwest::get(&url).await.unwrap_err());
+
+        assert!(format!("{:?}", err).contains("api_key=***"));
+        assert!(!format!("{:?}", err).contains("s3cr3t"));
+        assert!(!err.to_string().contains("s3cr3t"));
+    }
+
+    #[tokio::test]
+    async fn error_sources_mask_keys() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let url = format!(
+            "http://{}/2.0?method=track.love&api_key=s3cr3t&sk=s3ss10n",
+            addr
+        );
+        let err = Error::from(reqwest::get(&url).await.unwrap_err());
+
+        let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&err);
+        while let Some(e) = source {
+            for message in [e.to_string(), format!("{:?}", e)] {
+                assert!(!message.contains("s3cr3t"), "{}", message);
+                assert!(!message.contains("s3ss10n"), "{}", message);
+            }
+            source = e.source();
+        }
+    }
+
+    #[tokio::test]
+    async fn api_code_only_for_api_errors() {
+        let err = Error::Api(ErrorResponse {
+            error: ErrorCode::RateLimitExceeded,
+            message: "Rate Limit Exceeded".into(),
+        });
+        assert_eq!(err.api_code(), Some(&ErrorCode::RateLimitExceeded));
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let err = Error::from(reqwest::get(&format!("http://{}", addr)).await.unwrap_err());
+        assert_eq!(err.api_code(), None);
+    }
+
+    #[test]
+    fn from_serde_json_error_is_decode() {
+        let err = Error::from(serde_json::from_str::<ErrorResponse>("{").unwrap_err());
+        assert!(err.is_decode());
+        assert!(!err.is_connect());
+    }
+
+    #[test]
+    fn display_api_error() {
+        let err = Error::Api(ErrorResponse {
+            error: ErrorCode::InvalidAPIKey,
+            message: "Invalid API Key".into(),
+        });
+
+        assert_eq!(err.to_string(), "LastFM error (10): Invalid API Key");
+        assert!(std::error::Error::source(&err).is_none());
+    }
+
+    #[test]
+    fn decode_error_source() {
+        let err = Error::from(serde_json::from_str::<ErrorResponse>("{").unwrap_err());
+
+        assert!(err.to_string().starts_with("decode error: "));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn error_code_display() {
+        assert_eq!(
+            ErrorCode::InvalidAPIKey.to_string(),
+            "Invalid API key - You must be granted a valid key by last.fm"
+        );
+        assert_eq!(ErrorCode::InvalidAPIKey.code(), 10);
+
+        assert_eq!(
+            ErrorCode::RateLimitExceeded.to_string(),
+            "Rate Limit Exceeded - Your IP has made too many requests in a short period, exceeding our API guidelines"
+        );
+        assert_eq!(ErrorCode::RateLimitExceeded.code(), 29);
+    }
+
+    #[test]
+    fn deserialize_unknown_error_code() {
+        let json = json!({
+            "error": 19,
+            "message": "Something new"
+        });
+
+        let err: ErrorResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(err.error, ErrorCode::Unknown(19));
+        assert_eq!(err.error.code(), 19);
+        assert_eq!(err.message, "Something new");
+    }
 }
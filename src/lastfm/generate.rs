@@ -0,0 +1,234 @@
+use futures::future;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use std::collections::{HashSet, VecDeque};
+
+use super::{errors, Client, HttpClient, SimilarTrack};
+use crate::playlist::track_key;
+
+/// How many levels of similar tracks to expand beyond the seed.
+const MAX_DEPTH: usize = 3;
+/// How many of each track's similar tracks are themselves expanded.
+const EXPAND_TOP: usize = 5;
+
+/// A playlist being generated: the tracks yet to be expanded, along with
+/// their depth, the tracks already seen, and how many more are wanted.
+struct Expansion {
+    queue: VecDeque<(String, String, usize)>,
+    seen: HashSet<(String, String)>,
+    remaining: usize,
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Generate a playlist of up to `length` tracks similar to a seed track.
+    ///
+    /// The tracks similar to the seed come first. If there are fewer than
+    /// `length` of them, the tracks similar to the best matches are fetched
+    /// in turn, breadth-first, up to a few levels from the seed. Each track
+    /// appears at most once and the seed itself is never included.
+    ///
+    /// Only a failure to fetch the tracks similar to the seed fails the call.
+    /// Tracks whose similar tracks cannot be fetched are not expanded.
+    pub async fn generate_playlist(
+        &self,
+        seed_artist: &str,
+        seed_track: &str,
+        length: usize,
+    ) -> Result<Vec<SimilarTrack>, errors::Error> {
+        self.generate_playlist_stream(seed_artist, seed_track, length)
+            .try_collect()
+            .await
+    }
+
+    /// Like [`generate_playlist`](Client::generate_playlist), but yielding
+    /// the tracks found by each lookup as soon as it completes, rather than
+    /// once the whole playlist is known. A failure for the seed is yielded as
+    /// an item and ends the stream.
+    pub fn generate_playlist_stream<'a>(
+        &'a self,
+        seed_artist: &str,
+        seed_track: &str,
+        length: usize,
+    ) -> BoxStream<'a, Result<SimilarTrack, errors::Error>> {
+        let mut seen = HashSet::new();
+        seen.insert(track_key(seed_artist, seed_track));
+        let mut queue = VecDeque::new();
+        queue.push_back((seed_artist.to_owned(), seed_track.to_owned(), 0));
+        let expansion = Expansion {
+            queue,
+            seen,
+            remaining: length,
+        };
+
+        let batches = stream::unfold(expansion, move |mut expansion| async move {
+            let batch = self.expand_next(&mut expansion).await?;
+            Some((batch, expansion))
+        });
+        batches
+            .flat_map(|batch| match batch {
+                Ok(tracks) => stream::iter(tracks.into_iter().map(Ok)).left_stream(),
+                Err(e) => stream::once(future::ready(Err(e))).right_stream(),
+            })
+            .boxed()
+    }
+
+    /// Fetch the tracks similar to the next track to be expanded, returning
+    /// those not seen before, or `None` once the playlist is complete.
+    async fn expand_next(
+        &self,
+        expansion: &mut Expansion,
+    ) -> Option<Result<Vec<SimilarTrack>, errors::Error>> {
+        loop {
+            if expansion.remaining == 0 {
+                return None;
+            }
+            let (artist, track, depth) = expansion.queue.pop_front()?;
+
+            let similar = match self.similar_tracks(&artist, &track).await {
+                Ok(similar) => similar,
+                // Last.fm fails to find the similar tracks of many obscure
+                // tracks, which only matters for the seed.
+                Err(_) if depth > 0 => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            let mut tracks = Vec::new();
+            let mut expanded = 0;
+            for t in similar {
+                if tracks.len() >= expansion.remaining {
+                    break;
+                }
+                if !expansion.seen.insert(track_key(&t.artist.name, &t.name)) {
+                    continue;
+                }
+                if depth + 1 < MAX_DEPTH && expanded < EXPAND_TOP {
+                    expansion
+                        .queue
+                        .push_back((t.artist.name.clone(), t.name.clone(), depth + 1));
+                    expanded += 1;
+                }
+                tracks.push(t);
+            }
+            expansion.remaining -= tracks.len();
+
+            return Some(Ok(tracks));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::mock_client;
+    use serde_json::json;
+    use wiremock::matchers::query_param;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mock_similar(server: &MockServer, track: &str, similar: &[(&str, &str)]) {
+        let tracks: Vec<_> = similar
+            .iter()
+            .map(|(artist, name)| json!({"name": name, "artist": {"name": artist}, "match": 0.5}))
+            .collect();
+        Mock::given(query_param("method", "track.getsimilar"))
+            .and(query_param("track", track))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"similartracks": {"track": tracks}})),
+            )
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    fn names(playlist: &[SimilarTrack]) -> Vec<&str> {
+        playlist.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn expands_similar_tracks_breadth_first() {
+        let server = MockServer::start().await;
+        mock_similar(&server, "Seed", &[("A", "A1"), ("B", "B1")]).await;
+        mock_similar(&server, "A1", &[("A", "A2"), ("B", "B1"), ("X", "seed")]).await;
+        mock_similar(&server, "B1", &[("B", "B2"), ("A", "a2")]).await;
+
+        let playlist = mock_client(&server)
+            .generate_playlist("X", "Seed", 4)
+            .await
+            .unwrap();
+
+        assert_eq!(names(&playlist), vec!["A1", "B1", "A2", "B2"]);
+    }
+
+    #[tokio::test]
+    async fn streams_tracks_as_each_lookup_completes() {
+        let server = MockServer::start().await;
+        mock_similar(&server, "Seed", &[("A", "A1"), ("B", "B1")]).await;
+        mock_similar(&server, "A1", &[("A", "A2")]).await;
+        let client = mock_client(&server);
+
+        let mut tracks = client.generate_playlist_stream("X", "Seed", 3);
+        let first = tracks.next().await.unwrap().unwrap();
+        assert_eq!(first.name, "A1");
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+
+        let rest: Vec<_> = tracks.try_collect().await.unwrap();
+        assert_eq!(names(&rest), vec!["B1", "A2"]);
+    }
+
+    #[tokio::test]
+    async fn skips_tracks_which_cannot_be_expanded() {
+        let server = MockServer::start().await;
+        mock_similar(&server, "Seed", &[("A", "A1"), ("B", "B1")]).await;
+        Mock::given(query_param("method", "track.getsimilar"))
+            .and(query_param("track", "A1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": 6,
+                "message": "Track not found"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        mock_similar(&server, "B1", &[("B", "B2")]).await;
+
+        let playlist = mock_client(&server)
+            .generate_playlist("X", "Seed", 4)
+            .await
+            .unwrap();
+
+        assert_eq!(names(&playlist), vec!["A1", "B1", "B2"]);
+    }
+
+    #[tokio::test]
+    async fn fails_if_the_seed_cannot_be_expanded() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "track.getsimilar"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": 6,
+                "message": "Track not found"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server)
+            .generate_playlist("X", "Seed", 4)
+            .await
+            .unwrap_err();
+
+        assert!(err.api_code().is_some());
+    }
+
+    #[tokio::test]
+    async fn stops_when_candidates_are_exhausted() {
+        let server = MockServer::start().await;
+        mock_similar(&server, "Seed", &[("A", "A1")]).await;
+        mock_similar(&server, "A1", &[("A", "A2")]).await;
+        mock_similar(&server, "A2", &[("A", "A3")]).await;
+
+        let playlist = mock_client(&server)
+            .generate_playlist("X", "Seed", 10)
+            .await
+            .unwrap();
+
+        // A3 is beyond the maximum depth, so is never expanded
+        assert_eq!(names(&playlist), vec!["A1", "A2", "A3"]);
+    }
+}
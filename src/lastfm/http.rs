@@ -0,0 +1,236 @@
+//! The HTTP transport used by [`Client`](super::Client), which can be
+//! replaced to test code using the client without a server.
+
+use futures::future::BoxFuture;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode, Url};
+use std::time::Duration;
+
+use super::errors;
+
+/// A request for an [`HttpClient`] to send.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    /// Parameters to send as an `application/x-www-form-urlencoded` body
+    pub form: Option<Vec<(String, String)>>,
+    /// How long to wait for the request to complete
+    pub timeout: Duration,
+}
+
+impl HttpRequest {
+    /// The name of the API method requested, such as `track.getSimilar`, if
+    /// any.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn api_method(&self) -> Option<String> {
+        let from_query = self.url.query_pairs().find(|(k, _)| k == "method");
+        let from_form = self.form.iter().flatten().find(|(k, _)| k == "method");
+
+        from_query
+            .map(|(_, v)| v.into_owned())
+            .or_else(|| from_form.map(|(_, v)| v.clone()))
+    }
+}
+
+/// A request about to be sent, as passed to the hook set with
+/// [`ClientBuilder::on_request`](super::ClientBuilder::on_request).
+#[derive(Debug, Clone)]
+pub struct RequestLog {
+    pub method: Method,
+    /// The requested URL, with the API key and session key masked
+    pub url: Url,
+    /// The zero-based number of this attempt at the request, which is greater
+    /// than zero if the request is being retried
+    pub attempt: u32,
+}
+
+/// Query parameters which must never be logged.
+pub(crate) const SECRET_PARAMS: &[&str] = &["api_key", "sk"];
+
+/// A copy of `url` with the API key and session key masked, for logging.
+pub(crate) fn redact_url(url: &Url) -> Url {
+    let mut redacted = url.clone();
+    let pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    if pairs.is_empty() {
+        return redacted;
+    }
+
+    redacted
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs.iter().map(|(k, v)| {
+            if SECRET_PARAMS.contains(&k.as_str()) {
+                (k.as_str(), "***")
+            } else {
+                (k.as_str(), v.as_str())
+            }
+        }));
+
+    redacted
+}
+
+/// The response to an [`HttpRequest`].
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: String,
+}
+
+/// Sends the requests of a [`Client`](super::Client).
+///
+/// This is implemented for `reqwest::Client`, which is used by default.
+pub trait HttpClient: Send + Sync {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, errors::Error>>;
+}
+
+impl HttpClient for reqwest::Client {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, errors::Error>> {
+        Box::pin(async move {
+            let mut req = self
+                .request(request.method, request.url)
+                .headers(request.headers)
+                .timeout(request.timeout);
+            if let Some(form) = &request.form {
+                req = req.form(form);
+            }
+
+            let res = req.send().await?;
+            let status = res.status();
+            let headers = res.headers().clone();
+            let body = res.text().await?;
+
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
+
+/// Sends requests with a blocking client on tokio's blocking thread pool, so
+/// that it does not stall the runtime driving the [`Client`](super::Client).
+#[cfg(feature = "blocking")]
+impl HttpClient for reqwest::blocking::Client {
+    fn request(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, errors::Error>> {
+        let http = self.clone();
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || {
+                let mut req = http
+                    .request(request.method, request.url)
+                    .headers(request.headers)
+                    .timeout(request.timeout);
+                if let Some(form) = &request.form {
+                    req = req.form(form);
+                }
+
+                let res = req.send()?;
+                let status = res.status();
+                let headers = res.headers().clone();
+                let body = res.text()?;
+
+                Ok(HttpResponse {
+                    status,
+                    headers,
+                    body,
+                })
+            })
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::errors::ErrorCode;
+    use crate::lastfm::ClientBuilder;
+    use std::sync::Mutex;
+
+    /// Responds to every request with the same body, recording the requests.
+    struct Canned {
+        body: &'static str,
+        requests: Mutex<Vec<HttpRequest>>,
+    }
+
+    impl Canned {
+        fn new(body: &'static str) -> Self {
+            Canned {
+                body,
+                requests: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    impl HttpClient for Canned {
+        fn request(
+            &self,
+            request: HttpRequest,
+        ) -> BoxFuture<'_, Result<HttpResponse, errors::Error>> {
+            self.requests.lock().unwrap().push(request);
+            Box::pin(async move {
+                Ok(HttpResponse {
+                    status: StatusCode::OK,
+                    headers: HeaderMap::new(),
+                    body: self.body.to_owned(),
+                })
+            })
+        }
+    }
+
+    #[test]
+    fn redact_url_masks_keys() {
+        let url = Url::parse(
+            "http://ws.audioscrobbler.com/2.0?method=track.love&api_key=s3cr3t&sk=s3ss10n&track=Believe",
+        )
+        .unwrap();
+
+        assert_eq!(
+            redact_url(&url).as_str(),
+            "http://ws.audioscrobbler.com/2.0?method=track.love&api_key=***&sk=***&track=Believe"
+        );
+
+        let url = Url::parse("https://www.last.fm/player/station/user/sebnow/recommended").unwrap();
+        assert_eq!(redact_url(&url), url);
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_surfaces_api_errors() {
+        let client = ClientBuilder::new("key")
+            .build_with(Canned::new(r#"{"error": 6, "message": "Track not found"}"#));
+
+        let err = client.similar_tracks("Cher", "Nope").await.unwrap_err();
+
+        assert!(matches!(err, errors::Error::Api(e) if e.error == ErrorCode::InvalidParameters));
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_surfaces_malformed_responses() {
+        let canned = Canned::new("<html>Service Unavailable</html>");
+        let client = ClientBuilder::new("key").build_with(canned);
+
+        let err = client.similar_tracks("Cher", "Believe").await.unwrap_err();
+
+        assert!(err.is_decode());
+    }
+
+    #[tokio::test]
+    async fn passes_requests_to_the_http_client() {
+        let client = ClientBuilder::new("key")
+            .build_with(Canned::new(r#"{"similartracks": {"track": []}}"#));
+
+        let tracks = client.similar_tracks("Cher", "Believe").await.unwrap();
+
+        assert!(tracks.is_empty());
+        let requests = client.http.requests.lock().unwrap();
+        assert_eq!(requests[0].method, Method::GET);
+        assert_eq!(
+            requests[0].url.query_pairs().find(|(k, _)| k == "method"),
+            Some(("method".into(), "track.getsimilar".into()))
+        );
+    }
+}
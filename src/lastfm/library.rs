@@ -0,0 +1,148 @@
+use serde::Deserialize;
+
+use super::{de, errors, require, Client, HttpClient, Paged, Pagination};
+
+/// An artist in a user's library, as returned by `library.getArtists`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct LibraryArtist {
+    pub name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    /// How often the user has listened to the artist
+    #[serde(deserialize_with = "de::number")]
+    pub playcount: u64,
+    /// How many tags the user has applied to the artist
+    #[serde(deserialize_with = "de::number")]
+    pub tagcount: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct LibraryArtists {
+    artists: InnerLibraryArtists,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerLibraryArtists {
+    #[serde(rename = "artist", default, deserialize_with = "de::one_or_many")]
+    artists: Vec<LibraryArtist>,
+    #[serde(rename = "@attr")]
+    attr: Pagination,
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Fetch a page of the artists in a user's library, most listened to
+    /// first.
+    pub async fn library_artists(
+        &self,
+        user: &str,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<Paged<LibraryArtist>, errors::Error> {
+        require("user", user)?;
+
+        let mut params = vec![("user", user.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        let url = self.build_as_uri("library.getArtists", &params);
+
+        let library = self.get::<LibraryArtists>(url).await?.artists;
+
+        Ok(Paged {
+            items: library.artists,
+            pagination: library.attr,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::mock_client;
+    use serde_json::json;
+    use wiremock::matchers::query_param;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn library_artists_with_pagination() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "library.getArtists"))
+            .and(query_param("user", "RJ"))
+            .and(query_param("limit", "2"))
+            .and(query_param("page", "3"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"artists": {
+                "artist": [
+                    {
+                        "tagcount": "0",
+                        "image": [],
+                        "mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818",
+                        "url": "https://www.last.fm/music/Cher",
+                        "playcount": "1402",
+                        "name": "Cher",
+                        "streamable": "0"
+                    },
+                    {
+                        "tagcount": "3",
+                        "image": [],
+                        "mbid": "",
+                        "url": "https://www.last.fm/music/Dolly+Parton",
+                        "playcount": "860",
+                        "name": "Dolly Parton",
+                        "streamable": "0"
+                    }
+                ],
+                "@attr": {"page": "3", "total": "1501", "user": "RJ", "perPage": "2", "totalPages": "751"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let library = mock_client(&server)
+            .library_artists("RJ", Some(2), Some(3))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            library,
+            Paged {
+                items: vec![
+                    LibraryArtist {
+                        name: "Cher".into(),
+                        mbid: Some("bfcc6d75-a6a5-4bc6-8282-47aec8531818".into()),
+                        playcount: 1402,
+                        tagcount: 0,
+                    },
+                    LibraryArtist {
+                        name: "Dolly Parton".into(),
+                        mbid: None,
+                        playcount: 860,
+                        tagcount: 3,
+                    },
+                ],
+                pagination: Pagination {
+                    page: 3,
+                    per_page: 2,
+                    total_pages: 751,
+                    total: 1501,
+                },
+            }
+        );
+        assert_eq!(library.pagination.start_index(), 4);
+    }
+
+    #[tokio::test]
+    async fn library_artists_rejects_empty_user() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "library.getArtists"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server)
+            .library_artists("", None, None)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("user")));
+    }
+}
@@ -1,123 +1,1202 @@
 use reqwest;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
 
+mod album;
+mod artist;
+mod auth;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+mod builder;
+#[cfg(feature = "cache")]
+mod cache;
+mod chart;
+mod correction;
+mod de;
 pub mod errors;
+mod generate;
+mod http;
+mod library;
+mod pages;
+mod retry;
+mod scrobble;
+mod search;
+mod tag;
+mod track;
+mod user;
 
-static AS_BASE_URL: &'static str = "http://ws.audioscrobbler.com/2.0";
-static LAST_FM_BASE_URL: &'static str = "https://last.fm";
+pub use album::{AlbumInfo, AlbumMatch, AlbumTrack};
+pub use artist::{ArtistInfo, SimilarArtist, TopTrack};
+pub use auth::Session;
+pub use builder::ClientBuilder;
+#[cfg(feature = "cache")]
+pub use cache::{FileCache, ResponseCache};
+pub use chart::{ChartArtist, ChartTrack};
+pub use correction::{Corrected, Correction};
+pub use http::{HttpClient, HttpRequest, HttpResponse, RequestLog};
+pub use library::LibraryArtist;
+pub use pages::{Paged, Pages, Pagination};
+pub use retry::RetryPolicy;
+pub use scrobble::{NowPlaying, Scrobble, ScrobbleResult};
+pub use tag::{TagInfo, TagTrack};
+pub use track::{AlbumRef, SimilarTrack, SimilarTracksOptions, TrackInfo, TrackMatch};
+pub use user::{
+    ChartWeek, Friend, LovedTrack, RecentTrack, RecentTracksOptions, TaggedAlbum, TaggedItem,
+    TaggedTrack, TaggingType, UserInfo, UserTopArtist, UserTopTrack, WeeklyTrack,
+};
 
-#[derive(Deserialize, Debug, PartialEq)]
-struct SimilarTracks {
-    #[serde(rename = "similartracks")]
-    similar_tracks: InnerSimilarTracks,
+static AS_BASE_URL: &str = "http://ws.audioscrobbler.com/2.0";
+static LAST_FM_BASE_URL: &str = "https://www.last.fm";
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Artist {
+    pub name: String,
+    /// The artist's page on Last.fm
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(rename = "image", default, deserialize_with = "de::images")]
+    pub images: Images,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
-struct InnerSimilarTracks {
-    #[serde(rename = "track")]
-    pub tracks: Vec<SimilarTrack>,
+/// The URLs of an image at each of the sizes Last.fm provides, where
+/// available.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Images {
+    pub small: Option<String>,
+    pub medium: Option<String>,
+    pub large: Option<String>,
+    pub extralarge: Option<String>,
+    pub mega: Option<String>,
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
-pub struct Artist {
+pub struct Tag {
     pub name: String,
+    /// How often the tag was applied, relative to the most applied tag. This
+    /// is 0 where Last.fm does not report it, such as for `tag.getSimilar`.
+    #[serde(default, deserialize_with = "de::number")]
+    pub count: u64,
+    /// Number of distinct users who applied the tag. This is only reported
+    /// by the global `tag.getTopTags`, and is otherwise 0.
+    #[serde(default, deserialize_with = "de::number")]
+    pub reach: u64,
 }
 
+/// The `{"toptags": {"tag": [...]}}` response shared by the `getTopTags`
+/// methods.
 #[derive(Deserialize, Debug, PartialEq)]
-pub struct SimilarTrack {
-    pub name: String,
-    pub artist: Artist,
+struct TopTags {
+    #[serde(rename = "toptags")]
+    top_tags: InnerTopTags,
 }
 
 #[derive(Deserialize, Debug, PartialEq)]
+struct InnerTopTags {
+    #[serde(rename = "tag", default, deserialize_with = "de::one_or_many")]
+    tags: Vec<Tag>,
+    /// The tagged artist and track, after any autocorrection
+    #[serde(rename = "@attr", default)]
+    attr: Option<TopTagsAttr>,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+struct TopTagsAttr {
+    artist: Option<String>,
+    track: Option<String>,
+}
+
+/// The time range covered by a user's top artists, tracks or albums.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Period {
+    #[default]
+    Overall,
+    SevenDays,
+    OneMonth,
+    ThreeMonths,
+    SixMonths,
+    TwelveMonths,
+}
+
+impl Period {
+    /// The value of the `period` parameter expected by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Period::Overall => "overall",
+            Period::SevenDays => "7day",
+            Period::OneMonth => "1month",
+            Period::ThreeMonths => "3month",
+            Period::SixMonths => "6month",
+            Period::TwelveMonths => "12month",
+        }
+    }
+}
+
+impl std::fmt::Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Playlist {
     pub playlist: Vec<PlaylistItem>,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct PlaylistItem {
     pub name: String,
     pub artists: Vec<Artist>,
+    /// The track's page on Last.fm
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// Called with each request before it is sent.
+type RequestHook = Arc<dyn Fn(&RequestLog) + Send + Sync>;
+
+/// A client of the Last.fm API, sending requests with the [`HttpClient`] `H`.
+///
+/// The API key and secret are masked in its `Debug` output.
+#[derive(Clone)]
+pub struct Client<H = reqwest::Client> {
+    api_key: String,
+    http: H,
+    as_base_url: reqwest::Url,
+    last_fm_base_url: reqwest::Url,
+    retry: Option<RetryPolicy>,
+    #[cfg(feature = "cache")]
+    cache: Option<ResponseCache>,
+    #[cfg(feature = "cache")]
+    file_cache: Option<FileCache>,
+    timeout: Duration,
+    user_agent: String,
+    default_limit: Option<u32>,
+    default_page: Option<u32>,
+    on_request: Option<RequestHook>,
+    secret: Option<String>,
 }
 
-pub struct Client<'a> {
-    api_key: &'a str,
-    http: &'a reqwest::Client,
+impl<H: fmt::Debug> fmt::Debug for Client<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const MASK: &str = "***";
+
+        f.debug_struct("Client")
+            .field("api_key", &MASK)
+            .field("http", &self.http)
+            .field("as_base_url", &self.as_base_url.as_str())
+            .field("last_fm_base_url", &self.last_fm_base_url.as_str())
+            .field("retry", &self.retry)
+            .field("timeout", &self.timeout)
+            .field("user_agent", &self.user_agent)
+            .field("default_limit", &self.default_limit)
+            .field("default_page", &self.default_page)
+            .field("on_request", &self.on_request.as_ref().map(|_| "Fn"))
+            .field("secret", &self.secret.as_ref().map(|_| MASK))
+            .finish()
+    }
+}
+
+impl Client {
+    pub fn new(api_key: &str, client: &reqwest::Client) -> Self {
+        Client::owned(api_key.to_owned(), client.clone())
+    }
+
+    /// Create a client which takes ownership of the API key and HTTP client.
+    ///
+    /// `reqwest::Client` is reference counted internally, so handing over a
+    /// clone shares the underlying connection pool. The client is used as
+    /// configured, so its proxies, TLS settings, pool limits and default
+    /// headers all apply. The timeout and `User-Agent` configured through
+    /// [`ClientBuilder`] are still set on each request.
+    pub fn owned(api_key: String, http: reqwest::Client) -> Self {
+        ClientBuilder::new(api_key).http_client(http).build()
+    }
+
+    pub fn builder(api_key: impl Into<String>) -> ClientBuilder {
+        ClientBuilder::new(api_key)
+    }
 }
 
-impl<'a> Client<'a> {
-    pub fn new(api_key: &'a str, client: &'a reqwest::Client) -> Self {
+impl<H: HttpClient + Clone> Client<H> {
+    /// A copy of this client which sends `api_key` instead of its own key.
+    ///
+    /// The copy shares the HTTP client and caches of this one,
+    /// so it is cheap to create one per request. Cached responses are keyed by
+    /// the request URL, which includes the key, so they are not shared between
+    /// keys.
+    pub fn with_key(&self, api_key: impl Into<String>) -> Self {
         Client {
-            api_key,
-            http: client,
+            api_key: api_key.into(),
+            ..self.clone()
         }
     }
+}
 
-    pub async fn similar_tracks(
-        &self,
-        artist: &str,
-        track: &str,
-    ) -> Result<Vec<SimilarTrack>, errors::Error> {
-        let res = self
-            .http
-            .get(&self.build_as_uri(
-                "track.getsimilar",
-                &format!("artist={}&track={}", artist, track),
-            ))
-            .send()
-            .await?
-            .json::<SimilarTracks>().await?;
+/// Fail with [`errors::Error::InvalidInput`] if a required parameter is empty
+/// or only whitespace, which the API would reject anyway.
+fn require(name: &str, value: &str) -> Result<(), errors::Error> {
+    if value.trim().is_empty() {
+        return Err(errors::Error::InvalidInput(format!(
+            "{} must not be empty",
+            name
+        )));
+    }
+
+    Ok(())
+}
+
+impl<H: HttpClient> Client<H> {
+    /// The `limit` of a list method, or the default configured with
+    /// [`ClientBuilder::default_limit`] if none was given.
+    fn limit(&self, limit: Option<u32>) -> Option<u32> {
+        limit.or(self.default_limit)
+    }
 
-        Ok(res.similar_tracks.tracks)
+    /// The `page` of a list method, or the default configured with
+    /// [`ClientBuilder::default_page`] if none was given.
+    fn page(&self, page: Option<u32>) -> Option<u32> {
+        page.or(self.default_page)
     }
 
+    /// Fetch the tracks recommended to `user` by their last.fm radio station.
+    ///
+    /// This is not part of the web service, and the website may serve an HTML
+    /// page, such as a login wall, instead. That is reported as
+    /// [`errors::Error::UnexpectedContentType`].
     pub async fn user_recommended(&self, user: &str) -> Result<Playlist, errors::Error> {
-        let endpoint = format!("{}/player/station/user/{}/recommended", LAST_FM_BASE_URL, user);
+        require("user", user)?;
 
-        Ok(self.http.get(&endpoint).send().await?.json::<Playlist>().await?)
+        self.get(self.user_recommended_url(user)).await
     }
 
-    fn build_as_uri(&self, method: &str, params: &str) -> String {
-        format!(
-            "{}?method={}&api_key={}&format=json&{}",
-            AS_BASE_URL, method, self.api_key, params
-        )
+    /// The URL of `user`'s recommendations on the last.fm website. The user
+    /// name is percent-encoded as a single path segment.
+    fn user_recommended_url(&self, user: &str) -> reqwest::Url {
+        let mut url = self.last_fm_base_url.clone();
+        url.path_segments_mut()
+            .expect("last.fm base URL must be a valid base")
+            .pop_if_empty()
+            .extend(&["player", "station", "user", user, "recommended"]);
+
+        url
+    }
+
+    /// Call an API `method` with the given parameters, returning the
+    /// undecoded JSON response. This gives access to fields, and methods,
+    /// which are not modelled by this crate.
+    ///
+    /// Error responses are still reported as [`errors::Error::Api`].
+    pub async fn raw(
+        &self,
+        method: &str,
+        params: &[(&str, &str)],
+    ) -> Result<serde_json::Value, errors::Error> {
+        require("method", method)?;
+
+        self.get(self.build_as_uri(method, params)).await
+    }
+
+    fn build_as_uri<V: AsRef<str>>(&self, method: &str, params: &[(&str, V)]) -> reqwest::Url {
+        let mut url = self.as_base_url.clone();
+        url.query_pairs_mut()
+            .append_pair("method", method)
+            .append_pair("api_key", &self.api_key)
+            .append_pair("format", "json")
+            .extend_pairs(params.iter());
+
+        url
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: reqwest::Url) -> Result<T, errors::Error> {
+        #[cfg(feature = "cache")]
+        if cache::cacheable(reqwest::Method::GET, &url) {
+            if let Some(body) = self.cached(url.as_str()) {
+                return parse_response(reqwest::StatusCode::OK, &body);
+            }
+        }
+
+        self.send(self.http_request(reqwest::Method::GET, url, None))
+            .await
+    }
+
+    /// Build a request, with the headers and timeout sent with every request.
+    fn http_request(
+        &self,
+        method: reqwest::Method,
+        url: reqwest::Url,
+        form: Option<Vec<(String, String)>>,
+    ) -> HttpRequest {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Ok(user_agent) = self.user_agent.parse() {
+            headers.insert(reqwest::header::USER_AGENT, user_agent);
+        }
+
+        HttpRequest {
+            method,
+            url,
+            headers,
+            form,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Send a request, retrying it according to the retry policy.
+    #[cfg(not(feature = "tracing"))]
+    async fn send<T: DeserializeOwned>(&self, request: HttpRequest) -> Result<T, errors::Error> {
+        self.send_retrying(request).await
+    }
+
+    /// Send a request, retrying it according to the retry policy, within a
+    /// span recording the API method and how long the call took.
+    #[cfg(feature = "tracing")]
+    async fn send<T: DeserializeOwned>(&self, request: HttpRequest) -> Result<T, errors::Error> {
+        use tracing::Instrument;
+
+        let method = request.api_method().unwrap_or_default();
+        let span = tracing::debug_span!(
+            "lastfm_request",
+            method = method.as_str(),
+            elapsed_ms = tracing::field::Empty
+        );
+        let start = std::time::Instant::now();
+        let res = self.send_retrying(request).instrument(span.clone()).await;
+        span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+
+        res
+    }
+
+    async fn send_retrying<T: DeserializeOwned>(
+        &self,
+        request: HttpRequest,
+    ) -> Result<T, errors::Error> {
+        let mut attempt = 0;
+        loop {
+            let (res, retry_after) = self.send_once(request.clone(), attempt).await;
+            let delay = match (&res, &self.retry) {
+                (Err(e), Some(policy)) if policy.retries(&request.method) => {
+                    policy.delay(attempt, e, retry_after)
+                }
+                _ => None,
+            };
+
+            match delay {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return res,
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Send a single request, returning the decoded response along with the
+    /// delay requested by the `Retry-After` header, if any. `attempt` is the
+    /// zero-based number of the attempt, which is reported to the
+    /// `on_request` hook.
+    async fn send_once<T: DeserializeOwned>(
+        &self,
+        request: HttpRequest,
+        attempt: u32,
+    ) -> (Result<T, errors::Error>, Option<Duration>) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(url = %http::redact_url(&request.url), "sending request");
+        if let Some(on_request) = &self.on_request {
+            on_request(&RequestLog {
+                method: request.method.clone(),
+                url: http::redact_url(&request.url),
+                attempt,
+            });
+        }
+
+        #[cfg(feature = "cache")]
+        let cacheable = cache::cacheable(request.method.clone(), &request.url);
+        let request_url = request.url.clone();
+
+        let res = match self.http.request(request).await {
+            Ok(res) => res,
+            Err(e) => return (Err(e), None),
+        };
+        let retry_after = retry::retry_after(&res.headers);
+        let parsed = match self.unexpected_content_type(&request_url, &res) {
+            Some(content_type) => Err(errors::Error::UnexpectedContentType(content_type)),
+            None => parse_response(res.status, &res.body),
+        };
+
+        #[cfg(feature = "cache")]
+        if parsed.is_ok() && cacheable {
+            self.store(request_url.as_str(), res.body);
+        }
+
+        (parsed, retry_after)
+    }
+
+    /// The content type of a successful response from the last.fm website
+    /// which is not JSON. Unlike the web service, the website may respond with
+    /// an HTML page, such as when it requires the user to log in.
+    fn unexpected_content_type(&self, url: &reqwest::Url, res: &HttpResponse) -> Option<String> {
+        if !res.status.is_success() || url.origin() != self.last_fm_base_url.origin() {
+            return None;
+        }
+
+        let content_type = res.headers.get(reqwest::header::CONTENT_TYPE)?;
+        let content_type = String::from_utf8_lossy(content_type.as_bytes());
+        if content_type.contains("json") {
+            return None;
+        }
+
+        Some(content_type.into_owned())
     }
 }
 
+/// Response bodies longer than this are not included in
+/// [`errors::Error::HttpStatus`].
+const MAX_ERROR_BODY: usize = 256;
+
+/// Decode a response body into `T`.
+///
+/// Last.fm reports failures as an error document, often with a successful HTTP
+/// status, so the body is checked for one before decoding the expected type.
+/// Other unsuccessful statuses, such as from a proxy serving an HTML error
+/// page, are reported without attempting to decode the body.
+fn parse_response<T: DeserializeOwned>(
+    status: reqwest::StatusCode,
+    body: &str,
+) -> Result<T, errors::Error> {
+    if let Ok(err) = serde_json::from_str::<errors::ErrorResponse>(body) {
+        return Err(errors::Error::Api(err));
+    }
+
+    if !status.is_success() {
+        let body = Some(body.trim())
+            .filter(|b| !b.is_empty() && b.len() <= MAX_ERROR_BODY)
+            .map(str::to_owned);
+        return Err(errors::Error::HttpStatus(status, body));
+    }
+
+    Ok(serde_json::from_str(body)?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    pub(crate) fn mock_client(server: &MockServer) -> Client {
+        Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .build()
+    }
+
+    /// Like [`mock_client`], but able to sign requests.
+    pub(crate) fn signing_client(server: &MockServer) -> Client {
+        Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .secret("secret")
+            .build()
+    }
 
     #[test]
-    fn deserialize_similar_tracks() {
-        let json = json!({"similartracks": {
-            "track": [{
-                "name": "Strong Enough",
-                "playcount": 670120,
-                "mbid": "39473218-db80-4db2-9623-690b79b94e04",
-                "match": 1.0,
-                "artist": {
-                    "name": "Cher",
-                    "mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818"
-                }
-            }]
-        }});
+    fn parse_response_success() {
+        let body = r#"{"playlist": [{"name": "Believe", "artists": [{"name": "Cher"}]}]}"#;
 
-        let tracks: SimilarTracks = serde_json::from_value(json).unwrap();
+        let playlist: Playlist = parse_response(reqwest::StatusCode::OK, body).unwrap();
         assert_eq!(
-            tracks,
-            SimilarTracks {
-                similar_tracks: InnerSimilarTracks {
-                    tracks: vec![SimilarTrack {
-                        name: "Strong Enough".into(),
-                        artist: Artist {
-                            name: "Cher".into(),
-                        },
+            playlist,
+            Playlist {
+                playlist: vec![PlaylistItem {
+                    name: "Believe".into(),
+                    artists: vec![Artist {
+                        name: "Cher".into(),
+                        images: Default::default(),
+                        url: None,
                     }],
-                },
+                    url: None,
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_playlist_item_url() {
+        let with_url = json!({
+            "name": "Believe",
+            "url": "https://www.last.fm/music/Cher/_/Believe",
+            "artists": [{"name": "Cher", "url": "https://www.last.fm/music/Cher"}]
+        });
+        let item: PlaylistItem = serde_json::from_value(with_url).unwrap();
+        assert_eq!(
+            item.url.as_deref(),
+            Some("https://www.last.fm/music/Cher/_/Believe")
+        );
+        assert_eq!(
+            item.artists[0].url.as_deref(),
+            Some("https://www.last.fm/music/Cher")
+        );
+
+        let without_url = json!({"name": "Believe", "artists": [{"name": "Cher"}]});
+        let item: PlaylistItem = serde_json::from_value(without_url).unwrap();
+        assert_eq!(item.url, None);
+        assert_eq!(item.artists[0].url, None);
+    }
+
+    #[test]
+    fn parse_response_api_error() {
+        let body = r#"{"error": 10, "message": "Invalid API Key"}"#;
+
+        match parse_response::<Playlist>(reqwest::StatusCode::OK, body) {
+            Err(errors::Error::Api(e)) => assert_eq!(
+                e,
+                errors::ErrorResponse {
+                    error: errors::ErrorCode::InvalidAPIKey,
+                    message: "Invalid API Key".into(),
+                }
+            ),
+            res => panic!("expected an API error, got {:?}", res),
+        }
+    }
+
+    #[tokio::test]
+    async fn errors_distinguish_transport_decode_and_api() {
+        use std::error::Error as _;
+
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "track.getsimilar"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("{\"similartracks\":"))
+            .mount(&server)
+            .await;
+        Mock::given(query_param("method", "artist.getSimilar"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": 6,
+                "message": "The artist you supplied could not be found"
+            })))
+            .mount(&server)
+            .await;
+        let client = mock_client(&server);
+
+        let err = client.similar_tracks("Cher", "Believe").await.unwrap_err();
+        assert!(matches!(err, errors::Error::Decode(_)), "{:?}", err);
+        assert!(err.source().unwrap().is::<serde_json::Error>());
+
+        let err = client.similar_artists("Cher").await.unwrap_err();
+        assert!(matches!(err, errors::Error::Api(_)), "{:?}", err);
+        assert!(err.source().is_none());
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let unreachable = Client::builder("key")
+            .as_base_url(format!("http://{}/2.0", addr).parse().unwrap())
+            .build();
+        let err = unreachable
+            .similar_tracks("Cher", "Believe")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, errors::Error::Transport(_)), "{:?}", err);
+        assert!(err.source().unwrap().is::<reqwest::Error>());
+    }
+
+    #[test]
+    fn period_as_api_string() {
+        assert_eq!(Period::Overall.to_string(), "overall");
+        assert_eq!(Period::SevenDays.to_string(), "7day");
+        assert_eq!(Period::OneMonth.as_str(), "1month");
+        assert_eq!(Period::ThreeMonths.as_str(), "3month");
+        assert_eq!(Period::SixMonths.as_str(), "6month");
+        assert_eq!(Period::TwelveMonths.as_str(), "12month");
+        assert_eq!(Period::default(), Period::Overall);
+    }
+
+    #[test]
+    fn build_as_uri_encodes_params() {
+        let http = reqwest::Client::new();
+        let client = Client::new("key", &http);
+
+        let url = client.build_as_uri(
+            "track.getsimilar",
+            &[("artist", "Simon & Garfunkel"), ("track", "Mrs. Robinson")],
+        );
+        assert_eq!(
+            url.as_str(),
+            "http://ws.audioscrobbler.com/2.0?method=track.getsimilar&api_key=key&format=json&artist=Simon+%26+Garfunkel&track=Mrs.+Robinson"
+        );
+    }
+
+    #[tokio::test]
+    async fn with_key_overrides_api_key() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("api_key", "tenant-key"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        let tenant = client.with_key("tenant-key");
+
+        let url = tenant.build_as_uri("track.getsimilar", &[("mbid", "1")]);
+        assert!(url.as_str().contains("api_key=tenant-key"), "{}", url);
+        assert_eq!(client.api_key, "key");
+
+        tenant.similar_tracks("Cher", "Believe").await.unwrap();
+    }
+
+    #[test]
+    fn debug_masks_credentials() {
+        let client = Client::builder("s3cr3t-key").secret("s3cr3t").build();
+
+        let debug = format!("{:?}", client);
+        assert!(debug.contains(r#"api_key: "***""#), "{}", debug);
+        assert!(debug.contains(r#"secret: Some("***")"#), "{}", debug);
+        assert!(!debug.contains("s3cr3t"), "{}", debug);
+    }
+
+    #[test]
+    fn owned_client_can_be_moved_into_tasks() {
+        fn assert_send_static<T: Send + 'static>(_: T) {}
+
+        assert_send_static(Client::owned("key".into(), reqwest::Client::new()));
+    }
+
+    #[tokio::test]
+    async fn user_recommended_against_mock_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/player/station/user/sebnow/recommended"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"playlist": [
+                    {"name": "Believe", "artists": [{"name": "Cher"}]}
+                ]})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("key")
+            .last_fm_base_url(server.uri().parse().unwrap())
+            .build();
+
+        let playlist = client.user_recommended("sebnow").await.unwrap();
+        assert_eq!(playlist.playlist.len(), 1);
+        assert_eq!(playlist.playlist[0].name, "Believe");
+    }
+
+    #[test]
+    fn user_recommended_url_encodes_user() {
+        let client = Client::builder("key").build();
+        assert_eq!(
+            client.user_recommended_url("sebnow").as_str(),
+            "https://www.last.fm/player/station/user/sebnow/recommended"
+        );
+
+        let client = Client::builder("key")
+            .last_fm_base_url("http://localhost:4321/mirror/".parse().unwrap())
+            .build();
+        assert_eq!(
+            client.user_recommended_url("a user/with?odd#chars").as_str(),
+            "http://localhost:4321/mirror/player/station/user/a%20user%2Fwith%3Fodd%23chars/recommended"
+        );
+    }
+
+    #[tokio::test]
+    async fn user_recommended_reports_html_responses() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/player/station/user/sebnow/recommended"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_raw("<html>Log in</html>", "text/html"),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("key")
+            .last_fm_base_url(server.uri().parse().unwrap())
+            .build();
+
+        let err = client.user_recommended("sebnow").await.unwrap_err();
+        match err {
+            errors::Error::UnexpectedContentType(content_type) => {
+                assert_eq!(content_type, "text/html")
             }
+            err => panic!("expected a content type error, got {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn owned_preserves_http_client_settings() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header("x-custom", "preset"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-custom", "preset".parse().unwrap());
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()
+            .unwrap();
+
+        let mut client = Client::owned("key".into(), http);
+        client.as_base_url = format!("{}/2.0", server.uri()).parse().unwrap();
+
+        client.similar_tracks("Cher", "Believe").await.unwrap();
+    }
+
+    #[cfg(feature = "cache")]
+    #[tokio::test]
+    async fn serves_repeated_requests_from_cache() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {
+                    "track": [{"name": "Believe", "artist": {"name": "Cher"}, "match": 1.0}]
+                }})),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let cache = ResponseCache::new(
+            std::num::NonZeroUsize::new(16).unwrap(),
+            Duration::from_secs(60),
         );
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .cache(cache)
+            .build();
+
+        let first = client.similar_tracks("Cher", "Believe").await.unwrap();
+        let second = client.similar_tracks("Cher", "Believe").await.unwrap();
+        assert_eq!(first, second);
+
+        let err = client.similar_tracks("Cher", "Strong Enough").await;
+        assert!(matches!(err, Err(errors::Error::HttpStatus(..))));
+    }
+
+    #[cfg(feature = "cache")]
+    #[tokio::test]
+    async fn does_not_cache_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": 6,
+                "message": "Track not found"
+            })))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .cache(ResponseCache::new(
+                std::num::NonZeroUsize::new(16).unwrap(),
+                Duration::from_secs(60),
+            ))
+            .build();
+
+        assert!(client.similar_tracks("Cher", "Believe").await.is_err());
+        assert!(client.similar_tracks("Cher", "Believe").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn user_recommended_rejects_empty_user() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("key")
+            .last_fm_base_url(server.uri().parse().unwrap())
+            .build();
+
+        let err = client.user_recommended("").await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("user")));
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500).set_body_json(json!({
+                "error": 8,
+                "message": "Operation failed"
+            })))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let attempts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = attempts.clone();
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .retry(RetryPolicy::new(3, Duration::from_millis(1)))
+            .on_request(move |req| recorded.lock().unwrap().push(req.attempt))
+            .build();
+
+        let tracks = client.similar_tracks("Cher", "Believe").await.unwrap();
+        assert!(tracks.is_empty());
+        assert_eq!(*attempts.lock().unwrap(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_writes() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .secret("secret")
+            .retry(RetryPolicy::new(3, Duration::from_millis(1)))
+            .build();
+
+        let err = client
+            .love_track("sk", "Cher", "Believe")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, errors::Error::HttpStatus(..)));
+    }
+
+    #[tokio::test]
+    async fn retries_writes_when_enabled() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .secret("secret")
+            .retry(RetryPolicy::new(3, Duration::from_millis(1)).retry_writes())
+            .build();
+
+        client.love_track("sk", "Cher", "Believe").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_permanent_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+                "error": 10,
+                "message": "Invalid API Key"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .retry(RetryPolicy::new(3, Duration::from_millis(1)))
+            .build();
+
+        let err = client.similar_tracks("Cher", "Believe").await.unwrap_err();
+        assert!(matches!(err, errors::Error::Api(_)));
+    }
+
+    #[tokio::test]
+    async fn unsuccessful_status_is_an_error() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(500).set_body_string("<html>Internal Server Error</html>"),
+            )
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server)
+            .similar_tracks("Cher", "Believe")
+            .await
+            .unwrap_err();
+
+        match err {
+            errors::Error::HttpStatus(status, body) => {
+                assert_eq!(status, reqwest::StatusCode::INTERNAL_SERVER_ERROR);
+                assert_eq!(body.as_deref(), Some("<html>Internal Server Error</html>"));
+            }
+            err => panic!("expected a status error, got {:?}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503).set_body_string("x".repeat(1024)))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .mount(&server)
+            .await;
+
+        let attempts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = attempts.clone();
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .retry(RetryPolicy::new(2, Duration::from_millis(1)))
+            .on_request(move |req| recorded.lock().unwrap().push(req.attempt))
+            .build();
+
+        client.similar_tracks("Cher", "Believe").await.unwrap();
+        assert_eq!(*attempts.lock().unwrap(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn retries_rate_limited_requests_after_retry_after() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("Retry-After", "0")
+                    .set_body_json(json!({
+                        "error": 29,
+                        "message": "Rate Limit Exceeded"
+                    })),
+            )
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // The fallback delay is long enough to time out the test, so this
+        // only passes if the Retry-After header is honoured.
+        let attempts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = attempts.clone();
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .retry(
+                RetryPolicy::new(2, Duration::from_millis(1))
+                    .retry_rate_limited(Duration::from_secs(3600)),
+            )
+            .on_request(move |req| recorded.lock().unwrap().push(req.attempt))
+            .build();
+
+        let tracks = tokio::time::timeout(
+            Duration::from_secs(5),
+            client.similar_tracks("Cher", "Believe"),
+        )
+        .await
+        .expect("Retry-After should be honoured")
+        .unwrap();
+        assert!(tracks.is_empty());
+        assert_eq!(*attempts.lock().unwrap(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn slow_responses_time_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"similartracks": {"track": []}}))
+                    .set_delay(Duration::from_secs(5)),
+            )
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .timeout(Duration::from_millis(50))
+            .build();
+
+        let err = client.similar_tracks("Cher", "Believe").await.unwrap_err();
+        assert!(err.is_timeout(), "expected a timeout, got {:?}", err);
+    }
+
+    #[tokio::test]
+    async fn on_request_receives_redacted_urls() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .mount(&server)
+            .await;
+
+        let urls = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = urls.clone();
+        let client = Client::builder("s3cr3t")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .on_request(move |req| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push(format!("{} {}", req.method, req.url))
+            })
+            .build();
+
+        client.similar_tracks("Cher", "Believe").await.unwrap();
+
+        let urls = urls.lock().unwrap();
+        assert_eq!(urls.len(), 1);
+        assert!(urls[0].starts_with("GET http://"), "{}", urls[0]);
+        assert!(urls[0].contains("api_key=***"), "{}", urls[0]);
+        assert!(urls[0].contains("track=Believe"), "{}", urls[0]);
+        assert!(!urls[0].contains("s3cr3t"), "{}", urls[0]);
+    }
+
+    #[tokio::test]
+    async fn sends_user_agent() {
+        let server = MockServer::start().await;
+        Mock::given(header("User-Agent", "my-app/1.0"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = Client::builder("key")
+            .as_base_url(format!("{}/2.0", server.uri()).parse().unwrap())
+            .user_agent("my-app/1.0")
+            .build();
+
+        client.similar_tracks("Cher", "Believe").await.unwrap();
+    }
+
+    #[cfg(feature = "tracing")]
+    mod tracing_tests {
+        use super::*;
+        use std::sync::Mutex;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        type FieldValues = Vec<(String, String)>;
+
+        /// Records the fields of spans and events as strings.
+        #[derive(Clone, Default)]
+        struct Recorder {
+            spans: Arc<Mutex<Vec<(&'static str, FieldValues)>>>,
+            events: Arc<Mutex<Vec<FieldValues>>>,
+        }
+
+        #[derive(Default)]
+        struct Fields(FieldValues);
+
+        impl Visit for Fields {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0.push((field.name().to_owned(), value.to_owned()));
+            }
+
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .push((field.name().to_owned(), format!("{:?}", value)));
+            }
+        }
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut fields = Fields::default();
+                span.record(&mut fields);
+                let mut spans = self.spans.lock().unwrap();
+                spans.push((span.metadata().name(), fields.0));
+                Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                self.events.lock().unwrap().push(fields.0);
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        #[tokio::test]
+        async fn emits_span_per_request_without_api_key() {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(json!({"similartracks": {"track": []}})),
+                )
+                .mount(&server)
+                .await;
+            let recorder = Recorder::default();
+            let _guard = tracing::subscriber::set_default(recorder.clone());
+
+            mock_client(&server)
+                .similar_tracks("Cher", "Believe")
+                .await
+                .unwrap();
+
+            let spans = recorder.spans.lock().unwrap();
+            let (name, fields) = &spans[0];
+            assert_eq!(*name, "lastfm_request");
+            assert!(fields.contains(&("method".into(), "track.getsimilar".into())));
+
+            let events = recorder.events.lock().unwrap();
+            let url = events
+                .iter()
+                .flatten()
+                .find(|(k, _)| k == "url")
+                .map(|(_, v)| v)
+                .unwrap();
+            assert!(url.contains("api_key=***"), "{}", url);
+            assert!(!url.contains("api_key=key"), "{}", url);
+        }
     }
 }
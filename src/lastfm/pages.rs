@@ -0,0 +1,182 @@
+use futures::future::{self, Future};
+use futures::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use super::{de, errors};
+
+/// Where a page lies within a paginated list, as described by the `@attr`
+/// attributes of list methods.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pagination {
+    /// The 1-based number of this page
+    #[serde(deserialize_with = "de::number")]
+    pub page: u32,
+    #[serde(rename = "perPage", deserialize_with = "de::number")]
+    pub per_page: u32,
+    #[serde(rename = "totalPages", deserialize_with = "de::number")]
+    pub total_pages: u32,
+    /// The number of items across all pages
+    #[serde(deserialize_with = "de::number")]
+    pub total: u64,
+}
+
+impl Pagination {
+    /// The zero-based index of the first item on this page.
+    pub fn start_index(&self) -> u64 {
+        u64::from(self.page.saturating_sub(1)) * u64::from(self.per_page)
+    }
+}
+
+/// A page of a paginated list, such as search results or a user's loved
+/// tracks.
+#[derive(Debug, PartialEq)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub pagination: Pagination,
+}
+
+/// A stream of the items of a paginated list, fetching each page as the
+/// previous one is consumed.
+///
+/// The stream starts at the first page and ends after the last. An error
+/// fetching a page is yielded as an item and ends the stream.
+///
+/// ```no_run
+/// # async fn example(client: playlie::lastfm::Client) {
+/// use futures::StreamExt;
+///
+/// let mut tracks = client.user_loved_tracks_pages("sebnow");
+/// while let Some(track) = tracks.next().await {
+///     println!("{}", track.unwrap().name);
+/// }
+/// # }
+/// ```
+pub struct Pages<'a, T> {
+    inner: Pin<Box<dyn Stream<Item = Result<T, errors::Error>> + Send + 'a>>,
+}
+
+impl<'a, T: Send + 'a> Pages<'a, T> {
+    /// Create a stream over the pages returned by `fetch`, which is called
+    /// with each 1-based page number in turn.
+    pub(crate) fn new<F, Fut>(fetch: F) -> Self
+    where
+        F: FnMut(u32) -> Fut + Send + 'a,
+        Fut: Future<Output = Result<Paged<T>, errors::Error>> + Send + 'a,
+    {
+        let pages = stream::unfold((fetch, Some(1)), |(mut fetch, page)| async move {
+            let page = page?;
+            match fetch(page).await {
+                Ok(paged) => {
+                    let next = Some(page + 1).filter(|_| has_next_page(page, &paged));
+                    Some((Ok(paged.items), (fetch, next)))
+                }
+                Err(e) => Some((Err(e), (fetch, None))),
+            }
+        });
+        let items = pages.flat_map(|page| match page {
+            Ok(matches) => stream::iter(matches.into_iter().map(Ok)).left_stream(),
+            Err(e) => stream::once(future::ready(Err(e))).right_stream(),
+        });
+
+        Pages {
+            inner: Box::pin(items),
+        }
+    }
+}
+
+/// Whether there is a page after `page`, the number of the page requested.
+fn has_next_page<T>(page: u32, paged: &Paged<T>) -> bool {
+    !paged.items.is_empty() && page < paged.pagination.total_pages
+}
+
+impl<'a, T> Stream for Pages<'a, T> {
+    type Item = Result<T, errors::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::mock_client;
+    use serde_json::json;
+    use wiremock::matchers::query_param;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn loved_page(page: u32, name: &str) -> serde_json::Value {
+        json!({"lovedtracks": {
+            "track": [{
+                "artist": {"name": "Cher", "mbid": "", "url": "https://www.last.fm/music/Cher"},
+                "date": {"uts": "1613463960", "#text": "16 Feb 2021, 08:26"},
+                "mbid": "",
+                "url": "https://www.last.fm/music/Cher/_/Believe",
+                "name": name
+            }],
+            "@attr": {"user": "sebnow", "totalPages": "2", "page": page.to_string(), "perPage": "1", "total": "2"}
+        }})
+    }
+
+    #[test]
+    fn deserialize_pagination() {
+        let attr = json!({"user": "sebnow", "totalPages": "12", "page": "3", "perPage": "50", "total": "580"});
+
+        let pagination: Pagination = serde_json::from_value(attr).unwrap();
+        assert_eq!(
+            pagination,
+            Pagination {
+                page: 3,
+                per_page: 50,
+                total_pages: 12,
+                total: 580,
+            }
+        );
+        assert_eq!(pagination.start_index(), 100);
+    }
+
+    #[tokio::test]
+    async fn fetches_each_page_in_turn() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(loved_page(1, "Believe")))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(loved_page(2, "Strong Enough")))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        let names: Vec<_> = client
+            .user_loved_tracks_pages("sebnow")
+            .map(|t| t.unwrap().name)
+            .collect()
+            .await;
+
+        assert_eq!(names, vec!["Believe", "Strong Enough"]);
+    }
+
+    #[tokio::test]
+    async fn yields_errors_and_stops() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": 6,
+                "message": "User not found"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        let items: Vec<_> = client.user_loved_tracks_pages("nobody").collect().await;
+
+        assert_eq!(items.len(), 1);
+        assert!(matches!(items[0], Err(errors::Error::Api(_))));
+    }
+}
@@ -0,0 +1,374 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::errors::{Error, ErrorCode};
+
+/// The longest wait for a `Retry-After` header to be honoured by default.
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Controls how requests which failed with a transient error are retried.
+///
+/// Requests are retried when the API reports that the service is temporarily
+/// unavailable, when the server responds with a 5xx status, or when the
+/// request timed out or could not connect. The delay
+/// between attempts doubles after each retry.
+///
+/// Rate limited requests are only retried if enabled with
+/// [`RetryPolicy::retry_rate_limited`].
+///
+/// Only `GET` requests are retried unless enabled with
+/// [`RetryPolicy::retry_writes`], as a write such as a scrobble may have been
+/// accepted before the error arrived, and retrying it would repeat it.
+///
+/// With [`RetryPolicy::full_jitter`], each delay is instead a random duration
+/// up to the computed one, so that clients which failed together do not all
+/// retry at once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    rate_limit_delay: Option<Duration>,
+    max_retry_after: Duration,
+    jitter: Option<Jitter>,
+    retry_writes: bool,
+}
+
+/// A SplitMix64 generator, shared by clones of a policy so that they do not
+/// pick the same delays.
+#[derive(Clone)]
+struct Jitter(Arc<AtomicU64>);
+
+impl Jitter {
+    /// SplitMix64's increment, the odd integer closest to 2^64 / phi
+    const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+    fn new(seed: u64) -> Self {
+        Jitter(Arc::new(AtomicU64::new(seed)))
+    }
+
+    fn next_u64(&self) -> u64 {
+        let mut z = self
+            .0
+            .fetch_add(Self::GAMMA, Ordering::Relaxed)
+            .wrapping_add(Self::GAMMA);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random duration between zero and `max`, inclusive.
+    fn below(&self, max: Duration) -> Duration {
+        let max = u64::try_from(max.as_nanos()).unwrap_or(u64::MAX);
+        let nanos = (u128::from(self.next_u64()) * (u128::from(max) + 1)) >> 64;
+
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+impl fmt::Debug for Jitter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Jitter")
+    }
+}
+
+/// Generators are equal if they will produce the same numbers.
+impl PartialEq for Jitter {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.load(Ordering::Relaxed) == other.0.load(Ordering::Relaxed)
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy making at most `max_attempts` attempts in total,
+    /// waiting `base_delay` before the first retry.
+    pub fn new(max_attempts: u32, base_delay: Duration) -> Self {
+        RetryPolicy {
+            max_attempts,
+            base_delay,
+            rate_limit_delay: None,
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+            jitter: None,
+            retry_writes: false,
+        }
+    }
+
+    /// Wait a random duration between zero and the computed delay before
+    /// each retry. Delays requested by a `Retry-After` header are still
+    /// honoured exactly.
+    pub fn full_jitter(self) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+
+        self.full_jitter_seeded(seed)
+    }
+
+    /// Like [`full_jitter`](RetryPolicy::full_jitter), but with the random
+    /// delays generated from `seed`, so that they are reproducible.
+    pub fn full_jitter_seeded(mut self, seed: u64) -> Self {
+        self.jitter = Some(Jitter::new(seed));
+        self
+    }
+
+    /// Also retry requests rejected by rate limiting, waiting for as long as
+    /// the `Retry-After` response header asks, or `delay` if it is absent.
+    /// The wait is capped by [`cap_retry_after`](RetryPolicy::cap_retry_after).
+    pub fn retry_rate_limited(mut self, delay: Duration) -> Self {
+        self.rate_limit_delay = Some(delay);
+        self
+    }
+
+    /// Wait at most `max` when the `Retry-After` header asks for longer, so
+    /// that a misbehaving server cannot stall the caller. Defaults to 60
+    /// seconds.
+    pub fn cap_retry_after(mut self, max: Duration) -> Self {
+        self.max_retry_after = max;
+        self
+    }
+
+    /// Also retry signed `POST` requests, such as `track.scrobble`. These may
+    /// be repeated if the server accepted the request but failed to respond.
+    pub fn retry_writes(mut self) -> Self {
+        self.retry_writes = true;
+        self
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    pub fn base_delay(&self) -> Duration {
+        self.base_delay
+    }
+
+    pub fn rate_limit_delay(&self) -> Option<Duration> {
+        self.rate_limit_delay
+    }
+
+    pub fn max_retry_after(&self) -> Duration {
+        self.max_retry_after
+    }
+
+    /// Whether requests sent with `method` may be retried at all.
+    pub(crate) fn retries(&self, method: &reqwest::Method) -> bool {
+        self.retry_writes || method == reqwest::Method::GET
+    }
+
+    /// The delay before retrying after the given (zero-based) attempt failed,
+    /// or `None` if the request should not be retried. `retry_after` is the
+    /// delay requested by the server, if any.
+    pub(crate) fn delay(
+        &self,
+        attempt: u32,
+        err: &Error,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if attempt + 1 >= self.max_attempts {
+            return None;
+        }
+
+        if is_rate_limited(err) {
+            return match retry_after {
+                Some(retry_after) => self
+                    .rate_limit_delay
+                    .map(|_| retry_after.min(self.max_retry_after)),
+                None => self.rate_limit_delay.map(|d| self.jittered(d)),
+            };
+        }
+
+        if !is_retryable(err) {
+            return None;
+        }
+
+        Some(self.jittered(self.base_delay * 2u32.saturating_pow(attempt)))
+    }
+
+    /// `delay`, or a random duration up to it if jitter is enabled.
+    fn jittered(&self, delay: Duration) -> Duration {
+        match &self.jitter {
+            Some(jitter) => jitter.below(delay),
+            None => delay,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new(3, Duration::from_millis(500))
+    }
+}
+
+/// Parse a `Retry-After` header given in seconds. HTTP dates are not supported
+/// and are ignored.
+pub(crate) fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+
+    value.trim().parse().ok().map(Duration::from_secs)
+}
+
+fn is_rate_limited(err: &Error) -> bool {
+    match err {
+        Error::Api(e) => e.error == ErrorCode::RateLimitExceeded,
+        Error::HttpStatus(status, _) => *status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+        _ => false,
+    }
+}
+
+fn is_retryable(err: &Error) -> bool {
+    match err {
+        Error::Api(e) => matches!(
+            e.error,
+            ErrorCode::OperationFailed
+                | ErrorCode::ServiceOffline
+                | ErrorCode::ServiceTemporarilyUnavailable
+        ),
+        Error::Transport(e) => e.is_timeout() || e.is_connect(),
+        Error::HttpStatus(status, _) => status.is_server_error(),
+        Error::Decode(_)
+        | Error::MissingCredentials(_)
+        | Error::InvalidInput(_)
+        | Error::UnexpectedContentType(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::errors::ErrorResponse;
+
+    fn api_error(error: ErrorCode) -> Error {
+        Error::Api(ErrorResponse {
+            error,
+            message: String::new(),
+        })
+    }
+
+    #[test]
+    fn delay_doubles_per_attempt() {
+        let policy = RetryPolicy::new(4, Duration::from_millis(100));
+        let err = api_error(ErrorCode::ServiceOffline);
+
+        assert_eq!(
+            policy.delay(0, &err, None),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            policy.delay(1, &err, None),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            policy.delay(2, &err, None),
+            Some(Duration::from_millis(400))
+        );
+        assert_eq!(policy.delay(3, &err, None), None);
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        let policy = RetryPolicy::default();
+
+        assert!(policy
+            .delay(0, &api_error(ErrorCode::OperationFailed), None)
+            .is_some());
+        assert!(policy
+            .delay(
+                0,
+                &api_error(ErrorCode::ServiceTemporarilyUnavailable),
+                None
+            )
+            .is_some());
+        assert!(policy
+            .delay(0, &api_error(ErrorCode::InvalidAPIKey), None)
+            .is_none());
+    }
+
+    #[test]
+    fn rate_limited_requests_are_retried_when_enabled() {
+        let err = api_error(ErrorCode::RateLimitExceeded);
+        let policy = RetryPolicy::new(3, Duration::from_millis(100));
+        assert_eq!(policy.delay(0, &err, None), None);
+
+        let policy = policy.retry_rate_limited(Duration::from_secs(5));
+        assert_eq!(policy.delay(0, &err, None), Some(Duration::from_secs(5)));
+        assert_eq!(
+            policy.delay(0, &err, Some(Duration::from_secs(2))),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(policy.delay(2, &err, None), None);
+    }
+
+    #[test]
+    fn full_jitter_stays_within_backoff() {
+        let policy = RetryPolicy::new(5, Duration::from_millis(100)).full_jitter_seeded(42);
+        let replay = policy.clone().full_jitter_seeded(42);
+        let err = api_error(ErrorCode::ServiceOffline);
+
+        let delays: Vec<_> = (0..4)
+            .map(|attempt| policy.delay(attempt, &err, None).unwrap())
+            .collect();
+        for (attempt, delay) in delays.iter().enumerate() {
+            let backoff = Duration::from_millis(100) * 2u32.pow(attempt as u32);
+            assert!(*delay <= backoff, "{:?} > {:?}", delay, backoff);
+        }
+        assert!(delays.windows(2).any(|w| w[0] != w[1]), "{:?}", delays);
+
+        let replayed: Vec<_> = (0..4)
+            .map(|attempt| replay.delay(attempt, &err, None).unwrap())
+            .collect();
+        assert_eq!(delays, replayed);
+        assert_eq!(policy.delay(4, &err, None), None);
+    }
+
+    #[test]
+    fn full_jitter_honours_retry_after() {
+        let err = api_error(ErrorCode::RateLimitExceeded);
+        let policy = RetryPolicy::new(3, Duration::from_millis(100))
+            .retry_rate_limited(Duration::from_secs(5))
+            .full_jitter_seeded(7);
+
+        assert_eq!(
+            policy.delay(0, &err, Some(Duration::from_secs(2))),
+            Some(Duration::from_secs(2))
+        );
+        assert!(policy.delay(0, &err, None).unwrap() <= Duration::from_secs(5));
+    }
+
+    #[test]
+    fn retry_after_is_capped() {
+        let err = api_error(ErrorCode::RateLimitExceeded);
+        let policy = RetryPolicy::new(3, Duration::from_millis(100))
+            .retry_rate_limited(Duration::from_secs(5));
+        assert_eq!(
+            policy.delay(0, &err, Some(Duration::from_secs(999_999))),
+            Some(DEFAULT_MAX_RETRY_AFTER)
+        );
+
+        let policy = policy.cap_retry_after(Duration::from_secs(10));
+        assert_eq!(
+            policy.delay(0, &err, Some(Duration::from_secs(999_999))),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            policy.delay(0, &err, Some(Duration::from_secs(2))),
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn parse_retry_after() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(reqwest::header::RETRY_AFTER, "120".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(120)));
+
+        headers.insert(
+            reqwest::header::RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+}
@@ -0,0 +1,439 @@
+use serde::Deserialize;
+
+use super::{de, errors, require, Client, HttpClient};
+
+/// The most scrobbles `track.scrobble` accepts in a single request.
+const MAX_BATCH: usize = 50;
+
+/// A play of a track to submit with [`Client::scrobble`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scrobble {
+    pub artist: String,
+    pub track: String,
+    /// When the track started playing, as seconds since the Unix epoch
+    pub timestamp: u64,
+    pub album: Option<String>,
+    /// Length of the track in seconds
+    pub duration: Option<u64>,
+}
+
+/// Whether Last.fm accepted a submitted scrobble.
+#[derive(Debug, PartialEq)]
+pub struct ScrobbleResult {
+    /// The artist as recorded, after any corrections
+    pub artist: String,
+    /// The track as recorded, after any corrections
+    pub track: String,
+    pub accepted: bool,
+    /// Why the scrobble was ignored, if it was
+    pub ignored_message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ScrobbleResponse {
+    scrobbles: InnerScrobbles,
+}
+
+#[derive(Deserialize)]
+struct InnerScrobbles {
+    #[serde(rename = "scrobble", default, deserialize_with = "de::one_or_many")]
+    scrobbles: Vec<RawScrobbleResult>,
+}
+
+#[derive(Deserialize)]
+struct RawScrobbleResult {
+    #[serde(deserialize_with = "de::text")]
+    artist: String,
+    #[serde(deserialize_with = "de::text")]
+    track: String,
+    #[serde(rename = "ignoredMessage")]
+    ignored_message: IgnoredMessage,
+}
+
+#[derive(Deserialize)]
+struct IgnoredMessage {
+    /// 0 if the scrobble was accepted
+    #[serde(deserialize_with = "de::number")]
+    code: u32,
+    #[serde(rename = "#text")]
+    text: String,
+}
+
+impl From<RawScrobbleResult> for ScrobbleResult {
+    fn from(raw: RawScrobbleResult) -> Self {
+        let accepted = raw.ignored_message.code == 0;
+        ScrobbleResult {
+            artist: raw.artist,
+            track: raw.track,
+            accepted,
+            ignored_message: Some(raw.ignored_message.text).filter(|_| !accepted),
+        }
+    }
+}
+
+/// The track Last.fm recorded as playing, as echoed back by
+/// `track.updateNowPlaying` after any corrections.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct NowPlaying {
+    #[serde(deserialize_with = "de::text")]
+    pub artist: String,
+    #[serde(deserialize_with = "de::text")]
+    pub track: String,
+    #[serde(default, deserialize_with = "de::option_text")]
+    pub album: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NowPlayingResponse {
+    #[serde(rename = "nowplaying")]
+    now_playing: NowPlaying,
+}
+
+/// Encode scrobbles as the indexed `artist[0]`, `track[0]`, ... parameters
+/// of `track.scrobble`.
+fn scrobble_params(scrobbles: &[Scrobble]) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    for (i, s) in scrobbles.iter().enumerate() {
+        params.push((format!("artist[{}]", i), s.artist.clone()));
+        params.push((format!("track[{}]", i), s.track.clone()));
+        params.push((format!("timestamp[{}]", i), s.timestamp.to_string()));
+        if let Some(album) = &s.album {
+            params.push((format!("album[{}]", i), album.clone()));
+        }
+        if let Some(duration) = s.duration {
+            params.push((format!("duration[{}]", i), duration.to_string()));
+        }
+    }
+
+    params
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Submit plays on behalf of the user with the session key
+    /// `session_key`. Scrobbles are sent in batches of up to 50, and the
+    /// results of each batch are returned in order.
+    ///
+    /// A failed batch does not stop the others from being submitted, so the
+    /// plays recorded by the successful batches are still reported, and only
+    /// the failed batches need to be resubmitted. A batch containing a
+    /// scrobble without an artist or track fails with
+    /// [`errors::Error::InvalidInput`] and is not sent.
+    pub async fn scrobble(
+        &self,
+        session_key: &str,
+        scrobbles: &[Scrobble],
+    ) -> Vec<Result<Vec<ScrobbleResult>, errors::Error>> {
+        let mut results = Vec::new();
+        for batch in scrobbles.chunks(MAX_BATCH) {
+            results.push(self.scrobble_batch(session_key, batch).await);
+        }
+
+        results
+    }
+
+    async fn scrobble_batch(
+        &self,
+        session_key: &str,
+        batch: &[Scrobble],
+    ) -> Result<Vec<ScrobbleResult>, errors::Error> {
+        for scrobble in batch {
+            require("artist", &scrobble.artist)?;
+            require("track", &scrobble.track)?;
+        }
+
+        let params = scrobble_params(batch);
+        let params: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let res: ScrobbleResponse = self
+            .post_signed("track.scrobble", session_key, &params)
+            .await?;
+
+        Ok(res
+            .scrobbles
+            .scrobbles
+            .into_iter()
+            .map(ScrobbleResult::from)
+            .collect())
+    }
+
+    /// Tell Last.fm the user with the session key `session_key` has started
+    /// listening to a track. Unlike a scrobble, this does not record a play.
+    pub async fn update_now_playing(
+        &self,
+        session_key: &str,
+        artist: &str,
+        track: &str,
+        album: Option<&str>,
+        duration: Option<u64>,
+    ) -> Result<NowPlaying, errors::Error> {
+        require("artist", artist)?;
+        require("track", track)?;
+
+        let mut params = vec![("artist", artist.to_owned()), ("track", track.to_owned())];
+        params.extend(album.map(|a| ("album", a.to_owned())));
+        params.extend(duration.map(|d| ("duration", d.to_string())));
+        let res: NowPlayingResponse = self
+            .post_signed("track.updateNowPlaying", session_key, &params)
+            .await?;
+
+        Ok(res.now_playing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::auth::sign_params;
+    use crate::lastfm::errors::ErrorCode;
+    use crate::lastfm::tests::signing_client;
+    use serde_json::json;
+    use wiremock::matchers::{body_string_contains, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn scrobble(artist: &str, track: &str, timestamp: u64) -> Scrobble {
+        Scrobble {
+            artist: artist.into(),
+            track: track.into(),
+            timestamp,
+            album: None,
+            duration: None,
+        }
+    }
+
+    #[test]
+    fn encodes_indexed_params() {
+        let params = scrobble_params(&[
+            Scrobble {
+                album: Some("Believe".into()),
+                duration: Some(239),
+                ..scrobble("Cher", "Believe", 1287140447)
+            },
+            scrobble("Madonna", "Vogue", 1287140700),
+        ]);
+
+        let params: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            params,
+            vec![
+                ("artist[0]", "Cher"),
+                ("track[0]", "Believe"),
+                ("timestamp[0]", "1287140447"),
+                ("album[0]", "Believe"),
+                ("duration[0]", "239"),
+                ("artist[1]", "Madonna"),
+                ("track[1]", "Vogue"),
+                ("timestamp[1]", "1287140700"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn scrobble_posts_signed_batch() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("method=track.scrobble"))
+            .and(body_string_contains("artist%5B0%5D=Cher"))
+            .and(body_string_contains("track%5B1%5D=Vogue"))
+            .and(body_string_contains("sk=session"))
+            .and(body_string_contains("api_sig="))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"scrobbles": {
+                    "scrobble": [{
+                        "artist": {"corrected": "0", "#text": "Cher"},
+                        "album": {"corrected": "0", "#text": ""},
+                        "track": {"corrected": "0", "#text": "Believe"},
+                        "ignoredMessage": {"code": "0", "#text": ""},
+                        "albumArtist": {"corrected": "0", "#text": ""},
+                        "timestamp": "1287140447"
+                    }, {
+                        "artist": {"corrected": "0", "#text": "Madonna"},
+                        "album": {"corrected": "0", "#text": ""},
+                        "track": {"corrected": "0", "#text": "Vogue"},
+                        "ignoredMessage": {"code": "3", "#text": "Timestamp too old"},
+                        "albumArtist": {"corrected": "0", "#text": ""},
+                        "timestamp": "1287140700"
+                    }],
+                    "@attr": {"accepted": 1, "ignored": 1}
+                }})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = signing_client(&server);
+        let results = client
+            .scrobble(
+                "session",
+                &[
+                    scrobble("Cher", "Believe", 1287140447),
+                    scrobble("Madonna", "Vogue", 1287140700),
+                ],
+            )
+            .await;
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &vec![
+                ScrobbleResult {
+                    artist: "Cher".into(),
+                    track: "Believe".into(),
+                    accepted: true,
+                    ignored_message: None,
+                },
+                ScrobbleResult {
+                    artist: "Madonna".into(),
+                    track: "Vogue".into(),
+                    accepted: false,
+                    ignored_message: Some("Timestamp too old".into()),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn scrobble_splits_into_batches_of_fifty() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"scrobbles": {
+                    "scrobble": [],
+                    "@attr": {"accepted": 0, "ignored": 0}
+                }})),
+            )
+            .expect(2)
+            .mount(&server)
+            .await;
+
+        let client = signing_client(&server);
+        let scrobbles: Vec<_> = (0..51).map(|i| scrobble("Cher", "Believe", i)).collect();
+        let results = client.scrobble("session", &scrobbles).await;
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn scrobble_reports_batches_before_a_failure() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("timestamp%5B0%5D=0&"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"scrobbles": {
+                    "scrobble": {
+                        "artist": {"corrected": "0", "#text": "Cher"},
+                        "track": {"corrected": "0", "#text": "Believe"},
+                        "ignoredMessage": {"code": "0", "#text": ""}
+                    },
+                    "@attr": {"accepted": 1, "ignored": 0}
+                }})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("timestamp%5B0%5D=50&"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": 9,
+                "message": "Invalid session key - Please re-authenticate"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = signing_client(&server);
+        let scrobbles: Vec<_> = (0..51).map(|i| scrobble("Cher", "Believe", i)).collect();
+        let results = client.scrobble("session", &scrobbles).await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].as_ref().unwrap()[0].artist, "Cher");
+        assert!(matches!(
+            results[1],
+            Err(errors::Error::Api(ref e)) if e.error == ErrorCode::InvalidSessionKey
+        ));
+    }
+
+    #[tokio::test]
+    async fn update_now_playing_posts_signed_params() {
+        let server = MockServer::start().await;
+        let sig = sign_params(
+            &[
+                ("method", "track.updateNowPlaying"),
+                ("api_key", "key"),
+                ("artist", "cher"),
+                ("track", "believe"),
+                ("duration", "239"),
+                ("sk", "session"),
+            ],
+            "secret",
+        );
+        Mock::given(method("POST"))
+            .and(body_string_contains("method=track.updateNowPlaying"))
+            .and(body_string_contains("artist=cher"))
+            .and(body_string_contains("track=believe"))
+            .and(body_string_contains("duration=239"))
+            .and(body_string_contains("sk=session"))
+            .and(body_string_contains(format!("api_sig={}", sig)))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"nowplaying": {
+                    "artist": {"corrected": "1", "#text": "Cher"},
+                    "track": {"corrected": "1", "#text": "Believe"},
+                    "album": {"corrected": "0", "#text": ""},
+                    "albumArtist": {"corrected": "0", "#text": ""},
+                    "ignoredMessage": {"code": "0", "#text": ""}
+                }})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let now_playing = signing_client(&server)
+            .update_now_playing("session", "cher", "believe", None, Some(239))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            now_playing,
+            NowPlaying {
+                artist: "Cher".into(),
+                track: "Believe".into(),
+                album: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn scrobble_rejects_empty_input() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = signing_client(&server);
+
+        let results = client
+            .scrobble("session", &[scrobble("Cher", " ", 1287140447)])
+            .await;
+        assert!(
+            matches!(&results[..], [Err(errors::Error::InvalidInput(m))] if m.contains("track"))
+        );
+
+        let err = client
+            .update_now_playing("session", "", "Believe", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("artist")));
+
+        let err = client
+            .update_now_playing("", "Cher", "Believe", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("session_key")));
+    }
+}
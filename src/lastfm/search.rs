@@ -0,0 +1,54 @@
+use serde::Deserialize;
+use std::convert::TryFrom;
+
+use super::{de, Paged, Pagination};
+
+/// The OpenSearch `{"results": {...}}` response of the `search` methods.
+///
+/// The matches are nested under a key specific to each method, such as
+/// `artistmatches.artist`, so are decoded by `M` and converted into a list.
+#[derive(Deserialize, Debug)]
+pub(crate) struct SearchResponse<M> {
+    results: RawSearchResults<M>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RawSearchResults<M> {
+    #[serde(rename = "opensearch:totalResults", deserialize_with = "de::number")]
+    total_results: u64,
+    #[serde(rename = "opensearch:startIndex", deserialize_with = "de::number")]
+    start_index: u64,
+    #[serde(rename = "opensearch:itemsPerPage", deserialize_with = "de::number")]
+    items_per_page: u32,
+    #[serde(flatten)]
+    matches: M,
+}
+
+impl<M> SearchResponse<M> {
+    /// Convert the matches into a page, with the OpenSearch offsets
+    /// converted into page numbers.
+    pub(crate) fn into_paged<T>(self) -> Paged<T>
+    where
+        M: Into<Vec<T>>,
+    {
+        let results = self.results;
+        let per_page = u64::from(results.items_per_page);
+        let (page, total_pages) = match per_page {
+            0 => (1, 0),
+            _ => (
+                results.start_index / per_page + 1,
+                results.total_results.div_ceil(per_page),
+            ),
+        };
+
+        Paged {
+            items: results.matches.into(),
+            pagination: Pagination {
+                page: u32::try_from(page).unwrap_or(u32::MAX),
+                per_page: results.items_per_page,
+                total_pages: u32::try_from(total_pages).unwrap_or(u32::MAX),
+                total: results.total_results,
+            },
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use serde::Deserialize;
+
+use super::chart::TopArtists;
+use super::{
+    de, errors, require, AlbumMatch, Artist, ChartArtist, Client, HttpClient, Paged, Pagination,
+    Tag, TopTags,
+};
+
+/// One of the most popular tracks for a tag, as returned by
+/// `tag.getTopTracks`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct TagTrack {
+    pub name: String,
+    pub artist: Artist,
+    /// Position of the track in the tag's chart, starting at 1
+    #[serde(rename = "@attr", deserialize_with = "de::rank")]
+    pub rank: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct TagTracks {
+    tracks: InnerTagTracks,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerTagTracks {
+    #[serde(rename = "track", default, deserialize_with = "de::one_or_many")]
+    tracks: Vec<TagTrack>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TagAlbums {
+    albums: InnerTagAlbums,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerTagAlbums {
+    #[serde(rename = "album", default, deserialize_with = "de::one_or_many")]
+    albums: Vec<AlbumMatch>,
+    #[serde(rename = "@attr")]
+    attr: Pagination,
+}
+
+#[derive(Deserialize, Debug)]
+struct SimilarTags {
+    #[serde(rename = "similartags")]
+    similar_tags: InnerSimilarTags,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerSimilarTags {
+    #[serde(rename = "tag", default, deserialize_with = "de::one_or_many")]
+    tags: Vec<Tag>,
+}
+
+/// Information about a tag, as returned by `tag.getInfo`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct TagInfo {
+    pub name: String,
+    // Unlike most endpoints, tag.getInfo sends `total` and `reach` as JSON
+    // numbers rather than strings. `de::number` accepts either.
+    /// Number of times the tag was applied
+    #[serde(deserialize_with = "de::number")]
+    pub total: u64,
+    /// Number of distinct users who applied the tag
+    #[serde(deserialize_with = "de::number")]
+    pub reach: u64,
+    #[serde(rename = "wiki", default, deserialize_with = "de::wiki_summary")]
+    pub wiki_summary: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TagInfoResponse {
+    tag: TagInfo,
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Fetch the most popular tracks tagged with a genre such as "shoegaze",
+    /// ordered by rank.
+    pub async fn tag_top_tracks(
+        &self,
+        tag: &str,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<Vec<TagTrack>, errors::Error> {
+        require("tag", tag)?;
+
+        let mut params = vec![("tag", tag.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        let url = self.build_as_uri("tag.getTopTracks", &params);
+
+        Ok(self.get::<TagTracks>(url).await?.tracks.tracks)
+    }
+
+    /// Fetch a page of the most popular artists tagged with a genre, ordered
+    /// by rank.
+    pub async fn tag_top_artists(
+        &self,
+        tag: &str,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<Paged<ChartArtist>, errors::Error> {
+        require("tag", tag)?;
+
+        let mut params = vec![("tag", tag.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        let url = self.build_as_uri("tag.getTopArtists", &params);
+
+        Ok(self.get::<TopArtists>(url).await?.into())
+    }
+
+    /// Fetch a page of the most popular albums tagged with a genre, ordered
+    /// by rank.
+    pub async fn tag_top_albums(
+        &self,
+        tag: &str,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<Paged<AlbumMatch>, errors::Error> {
+        require("tag", tag)?;
+
+        let mut params = vec![("tag", tag.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        let url = self.build_as_uri("tag.getTopAlbums", &params);
+
+        let albums = self.get::<TagAlbums>(url).await?.albums;
+
+        Ok(Paged {
+            items: albums.albums,
+            pagination: albums.attr,
+        })
+    }
+
+    /// Fetch the tags most similar to a tag, such as "dream pop" for
+    /// "shoegaze".
+    pub async fn similar_tags(&self, tag: &str) -> Result<Vec<Tag>, errors::Error> {
+        require("tag", tag)?;
+
+        let url = self.build_as_uri("tag.getSimilar", &[("tag", tag)]);
+
+        Ok(self.get::<SimilarTags>(url).await?.similar_tags.tags)
+    }
+
+    /// Fetch the most used tags across Last.fm, most applied first.
+    pub async fn top_tags(&self) -> Result<Vec<Tag>, errors::Error> {
+        let url = self.build_as_uri::<&str>("tag.getTopTags", &[]);
+
+        Ok(self.get::<TopTags>(url).await?.top_tags.tags)
+    }
+
+    /// Fetch how widely a tag is used, along with a summary of its wiki.
+    pub async fn tag_info(&self, tag: &str) -> Result<TagInfo, errors::Error> {
+        require("tag", tag)?;
+
+        let url = self.build_as_uri("tag.getInfo", &[("tag", tag)]);
+
+        Ok(self.get::<TagInfoResponse>(url).await?.tag)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::mock_client;
+    use serde_json::json;
+    use wiremock::matchers::query_param;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn tag_top_tracks_with_multi_word_tag() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "tag.getTopTracks"))
+            .and(query_param("tag", "dream pop"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"tracks": {
+                "track": [{
+                    "name": "Space Song",
+                    "duration": "320",
+                    "mbid": "",
+                    "url": "https://www.last.fm/music/Beach+House/_/Space+Song",
+                    "streamable": {"#text": "0", "fulltrack": "0"},
+                    "artist": {"name": "Beach House", "mbid": "", "url": "https://www.last.fm/music/Beach+House"},
+                    "image": [],
+                    "@attr": {"rank": "1"}
+                }, {
+                    "name": "Cherry-Coloured Funk",
+                    "duration": "192",
+                    "mbid": "",
+                    "url": "https://www.last.fm/music/Cocteau+Twins/_/Cherry-Coloured+Funk",
+                    "artist": {"name": "Cocteau Twins", "mbid": "", "url": "https://www.last.fm/music/Cocteau+Twins"},
+                    "@attr": {"rank": "2"}
+                }],
+                "@attr": {"tag": "dream pop", "page": "1", "perPage": "2", "totalPages": "500", "total": "1000"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tracks = mock_client(&server)
+            .tag_top_tracks("dream pop", Some(2), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tracks,
+            vec![
+                TagTrack {
+                    name: "Space Song".into(),
+                    artist: Artist {
+                        name: "Beach House".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Beach+House".into()),
+                    },
+                    rank: 1,
+                },
+                TagTrack {
+                    name: "Cherry-Coloured Funk".into(),
+                    artist: Artist {
+                        name: "Cocteau Twins".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Cocteau+Twins".into()),
+                    },
+                    rank: 2,
+                },
+            ]
+        );
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0].url.query().unwrap().contains("tag=dream+pop"));
+    }
+
+    #[test]
+    fn deserialize_similar_tags() {
+        let json = json!({"similartags": {
+            "tag": [
+                {"name": "dream pop", "url": "https://www.last.fm/tag/dream+pop", "streamable": "1"},
+                {"name": "noise pop", "url": "https://www.last.fm/tag/noise+pop", "streamable": "1"}
+            ],
+            "@attr": {"tag": "shoegaze"}
+        }});
+
+        let similar: SimilarTags = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            similar.similar_tags.tags,
+            vec![
+                Tag {
+                    name: "dream pop".into(),
+                    count: 0,
+                    reach: 0,
+                },
+                Tag {
+                    name: "noise pop".into(),
+                    count: 0,
+                    reach: 0,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_tag_info() {
+        let json = json!({"tag": {
+            "name": "shoegaze",
+            "total": 402947,
+            "reach": 41130,
+            "wiki": {
+                "summary": "Shoegaze is a subgenre of alternative rock.",
+                "content": "Shoegaze is a subgenre of alternative rock. It emerged in the UK."
+            }
+        }});
+
+        let info: TagInfoResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            info.tag,
+            TagInfo {
+                name: "shoegaze".into(),
+                total: 402947,
+                reach: 41130,
+                wiki_summary: Some("Shoegaze is a subgenre of alternative rock.".into()),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn tag_top_artists_paged() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "tag.getTopArtists"))
+            .and(query_param("tag", "dream pop"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"topartists": {
+                "artist": [{
+                    "name": "Beach House",
+                    "mbid": "d5cc67b8-1cc4-453b-96e8-44487acdebea",
+                    "url": "https://www.last.fm/music/Beach+House",
+                    "streamable": "0",
+                    "image": [],
+                    "@attr": {"rank": "3"}
+                }],
+                "@attr": {"tag": "dream pop", "page": "2", "perPage": "2", "totalPages": "3", "total": "6"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let artists = mock_client(&server)
+            .tag_top_artists("dream pop", None, Some(2))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            artists,
+            Paged {
+                items: vec![ChartArtist {
+                    name: "Beach House".into(),
+                    mbid: Some("d5cc67b8-1cc4-453b-96e8-44487acdebea".into()),
+                    playcount: 0,
+                    listeners: 0,
+                }],
+                pagination: Pagination {
+                    page: 2,
+                    per_page: 2,
+                    total_pages: 3,
+                    total: 6,
+                },
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn tag_top_albums_paged() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "tag.getTopAlbums"))
+            .and(query_param("tag", "dream pop"))
+            .and(query_param("limit", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"albums": {
+                "album": {
+                    "name": "Heaven or Las Vegas",
+                    "mbid": "",
+                    "url": "https://www.last.fm/music/Cocteau+Twins/Heaven+or+Las+Vegas",
+                    "artist": {
+                        "name": "Cocteau Twins",
+                        "mbid": "",
+                        "url": "https://www.last.fm/music/Cocteau+Twins"
+                    },
+                    "image": [],
+                    "@attr": {"rank": "1"}
+                },
+                "@attr": {"tag": "dream pop", "page": "1", "perPage": "1", "totalPages": "100", "total": "100"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let albums = mock_client(&server)
+            .tag_top_albums("dream pop", Some(1), None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            albums.items,
+            vec![AlbumMatch {
+                name: "Heaven or Las Vegas".into(),
+                artist: "Cocteau Twins".into(),
+                mbid: None,
+                url: "https://www.last.fm/music/Cocteau+Twins/Heaven+or+Las+Vegas".into(),
+            }]
+        );
+        assert_eq!(albums.pagination.total_pages, 100);
+    }
+
+    #[tokio::test]
+    async fn top_tags_with_reach() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "tag.getTopTags"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"toptags": {
+                "@attr": {"offset": 0, "num_res": 50, "total": 2800},
+                "tag": [
+                    {"name": "rock", "count": 4024338, "reach": 398821},
+                    {"name": "electronic", "count": 2465714, "reach": 262043}
+                ]
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tags = mock_client(&server).top_tags().await.unwrap();
+
+        assert_eq!(
+            tags,
+            vec![
+                Tag {
+                    name: "rock".into(),
+                    count: 4024338,
+                    reach: 398821,
+                },
+                Tag {
+                    name: "electronic".into(),
+                    count: 2465714,
+                    reach: 262043,
+                },
+            ]
+        );
+    }
+}
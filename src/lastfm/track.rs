@@ -0,0 +1,1378 @@
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+
+use super::search::SearchResponse;
+use super::{
+    de, errors, require, Artist, Client, Corrected, HttpClient, Images, InnerTopTags, Paged, Tag,
+    TopTags,
+};
+
+/// The most tags `track.addTags` accepts in one request.
+const MAX_TAGS: usize = 10;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct SimilarTracks {
+    #[serde(rename = "similartracks")]
+    similar_tracks: InnerSimilarTracks,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct InnerSimilarTracks {
+    #[serde(rename = "track", default, deserialize_with = "de::one_or_many")]
+    pub tracks: Vec<SimilarTrack>,
+    /// The seed track, after any autocorrection
+    #[serde(rename = "@attr", default)]
+    pub attr: Option<SimilarTracksAttr>,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+struct SimilarTracksAttr {
+    artist: Option<String>,
+    track: Option<String>,
+}
+
+/// A track similar to the seed track.
+///
+/// Tracks are compared and hashed by their name, artist and MusicBrainz ID
+/// alone, so that the same track found from several seeds, with different
+/// match scores, is kept once in a set.
+#[derive(Deserialize, Clone, Debug)]
+pub struct SimilarTrack {
+    pub name: String,
+    pub artist: Artist,
+    /// How similar the track is to the seed track, from 0 to 1
+    #[serde(rename = "match", deserialize_with = "de::number")]
+    pub match_score: f64,
+    #[serde(default, deserialize_with = "de::option_number")]
+    pub playcount: Option<u64>,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    /// The track's page on Last.fm
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(rename = "image", default, deserialize_with = "de::images")]
+    pub images: Images,
+}
+
+impl PartialEq for SimilarTrack {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.artist.name == other.artist.name && self.mbid == other.mbid
+    }
+}
+
+impl Eq for SimilarTrack {}
+
+impl SimilarTrack {
+    /// The match score clamped to the range 0 to 1, so that it can be ranked
+    /// alongside scores from other sources. An undefined score is 0.
+    pub fn normalized_match(&self) -> f64 {
+        if self.match_score.is_nan() {
+            return 0.0;
+        }
+
+        self.match_score.clamp(0.0, 1.0)
+    }
+}
+
+impl std::hash::Hash for SimilarTrack {
+    fn hash<S: std::hash::Hasher>(&self, state: &mut S) {
+        self.name.hash(state);
+        self.artist.name.hash(state);
+        self.mbid.hash(state);
+    }
+}
+
+/// Detailed information about a track, as returned by `track.getInfo`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct TrackInfo {
+    pub name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    pub url: Option<String>,
+    /// Length of the track in milliseconds, if known
+    #[serde(default, deserialize_with = "de::option_number")]
+    pub duration: Option<u64>,
+    #[serde(deserialize_with = "de::number")]
+    pub listeners: u64,
+    #[serde(deserialize_with = "de::number")]
+    pub playcount: u64,
+    pub artist: Artist,
+    pub album: Option<AlbumRef>,
+    #[serde(rename = "toptags", default, deserialize_with = "de::tag_names")]
+    pub tags: Vec<String>,
+    #[serde(rename = "wiki", default, deserialize_with = "de::wiki_summary")]
+    pub wiki_summary: Option<String>,
+}
+
+/// The album a track appears on.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct AlbumRef {
+    pub artist: String,
+    pub title: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    pub url: Option<String>,
+    #[serde(rename = "image", default, deserialize_with = "de::images")]
+    pub images: Images,
+}
+
+/// A track matching a search, as returned by `track.search`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct TrackMatch {
+    pub name: String,
+    pub artist: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    #[serde(deserialize_with = "de::number")]
+    pub listeners: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct TrackMatches {
+    #[serde(rename = "trackmatches")]
+    track_matches: InnerTrackMatches,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerTrackMatches {
+    #[serde(default, deserialize_with = "de::one_or_many")]
+    track: Vec<TrackMatch>,
+}
+
+impl From<TrackMatches> for Vec<TrackMatch> {
+    fn from(matches: TrackMatches) -> Self {
+        matches.track_matches.track
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct TrackInfoResponse {
+    track: TrackInfo,
+}
+
+/// Options for [`Client::similar_tracks_with`], identifying the seed track
+/// either by MusicBrainz ID or by artist and track name.
+///
+/// ```
+/// use playlie::lastfm::SimilarTracksOptions;
+///
+/// let opts = SimilarTracksOptions::new()
+///     .artist("Cher")
+///     .track("Believe")
+///     .limit(10)
+///     .autocorrect(true);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimilarTracksOptions {
+    artist: Option<String>,
+    track: Option<String>,
+    mbid: Option<String>,
+    limit: Option<u32>,
+    autocorrect: bool,
+}
+
+impl SimilarTracksOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn artist(mut self, artist: impl Into<String>) -> Self {
+        self.artist = Some(artist.into());
+        self
+    }
+
+    pub fn track(mut self, track: impl Into<String>) -> Self {
+        self.track = Some(track.into());
+        self
+    }
+
+    /// Identify the seed track by MusicBrainz ID, which takes precedence over
+    /// the artist and track.
+    pub fn mbid(mut self, mbid: impl Into<String>) -> Self {
+        self.mbid = Some(mbid.into());
+        self
+    }
+
+    /// Fetch at most `limit` tracks.
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Let Last.fm correct misspelled artist and track names. Disabled by
+    /// default.
+    pub fn autocorrect(mut self, autocorrect: bool) -> Self {
+        self.autocorrect = autocorrect;
+        self
+    }
+}
+
+impl<H: HttpClient> Client<H> {
+    pub async fn similar_tracks(
+        &self,
+        artist: &str,
+        track: &str,
+    ) -> Result<Vec<SimilarTrack>, errors::Error> {
+        self.similar_tracks_with(&SimilarTracksOptions::new().artist(artist).track(track))
+            .await
+    }
+
+    /// Fetch tracks similar to a track, as configured by `opts`. Fails with
+    /// [`errors::Error::InvalidInput`] unless either an mbid, or an artist
+    /// and track, is given.
+    pub async fn similar_tracks_with(
+        &self,
+        opts: &SimilarTracksOptions,
+    ) -> Result<Vec<SimilarTrack>, errors::Error> {
+        Ok(self.similar_tracks_response(opts).await?.tracks)
+    }
+
+    async fn similar_tracks_response(
+        &self,
+        opts: &SimilarTracksOptions,
+    ) -> Result<InnerSimilarTracks, errors::Error> {
+        let mut params = match (&opts.mbid, &opts.artist, &opts.track) {
+            (Some(mbid), _, _) => {
+                require("mbid", mbid)?;
+                vec![("mbid", mbid.clone())]
+            }
+            (None, Some(artist), Some(track)) => {
+                require("artist", artist)?;
+                require("track", track)?;
+                vec![("artist", artist.clone()), ("track", track.clone())]
+            }
+            _ => {
+                return Err(errors::Error::InvalidInput(
+                    "either an mbid or an artist and track must be given".into(),
+                ))
+            }
+        };
+        params.extend(self.limit(opts.limit).map(|l| ("limit", l.to_string())));
+        if opts.autocorrect {
+            params.push(("autocorrect", "1".into()));
+        }
+        let url = self.build_as_uri("track.getsimilar", &params);
+
+        Ok(self.get::<SimilarTracks>(url).await?.similar_tracks)
+    }
+
+    /// Fetch tracks similar to the given track as undecoded JSON, for access
+    /// to fields not modelled by [`SimilarTrack`].
+    pub async fn similar_tracks_raw(
+        &self,
+        artist: &str,
+        track: &str,
+    ) -> Result<serde_json::Value, errors::Error> {
+        require("artist", artist)?;
+        require("track", track)?;
+
+        self.raw("track.getsimilar", &[("artist", artist), ("track", track)])
+            .await
+    }
+
+    /// Fetch at most `limit` tracks similar to the given track.
+    pub async fn similar_tracks_limited(
+        &self,
+        artist: &str,
+        track: &str,
+        limit: u32,
+    ) -> Result<Vec<SimilarTrack>, errors::Error> {
+        let opts = SimilarTracksOptions::new()
+            .artist(artist)
+            .track(track)
+            .limit(limit);
+
+        self.similar_tracks_with(&opts).await
+    }
+
+    /// Fetch tracks similar to the given track, letting Last.fm correct
+    /// misspelled artist and track names. Any corrected names are returned
+    /// along with the tracks.
+    pub async fn similar_tracks_autocorrected(
+        &self,
+        artist: &str,
+        track: &str,
+    ) -> Result<Corrected<Vec<SimilarTrack>>, errors::Error> {
+        let opts = SimilarTracksOptions::new()
+            .artist(artist)
+            .track(track)
+            .autocorrect(true);
+        let res = self.similar_tracks_response(&opts).await?;
+        let attr = res.attr.unwrap_or_default();
+
+        Ok(Corrected::new(res.tracks)
+            .artist(artist, attr.artist)
+            .track(track, attr.track))
+    }
+
+    /// Fetch tracks similar to the recording with the given MusicBrainz ID.
+    pub async fn similar_tracks_by_mbid(
+        &self,
+        mbid: &str,
+    ) -> Result<Vec<SimilarTrack>, errors::Error> {
+        self.similar_tracks_with(&SimilarTracksOptions::new().mbid(mbid))
+            .await
+    }
+
+    /// Fetch the tracks similar to each `(artist, track)` seed, making at most
+    /// `concurrency` requests at a time. The results are in the same order as
+    /// the seeds.
+    pub async fn similar_tracks_many(
+        &self,
+        seeds: &[(String, String)],
+        concurrency: usize,
+    ) -> Vec<Result<Vec<SimilarTrack>, errors::Error>> {
+        let mut results: Vec<_> = stream::iter(seeds.iter().enumerate())
+            .map(
+                |(i, (artist, track))| async move { (i, self.similar_tracks(artist, track).await) },
+            )
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
+        results.sort_by_key(|(i, _)| *i);
+
+        results.into_iter().map(|(_, res)| res).collect()
+    }
+
+    /// Fetch detailed information about a track.
+    pub async fn track_info(&self, artist: &str, track: &str) -> Result<TrackInfo, errors::Error> {
+        require("artist", artist)?;
+        require("track", track)?;
+
+        let url = self.build_as_uri("track.getInfo", &[("artist", artist), ("track", track)]);
+
+        Ok(self.get::<TrackInfoResponse>(url).await?.track)
+    }
+
+    /// Fetch the tags most often applied to a track.
+    pub async fn track_top_tags(
+        &self,
+        artist: &str,
+        track: &str,
+    ) -> Result<Vec<Tag>, errors::Error> {
+        Ok(self.fetch_track_top_tags(artist, track, false).await?.tags)
+    }
+
+    /// Fetch the tags most often applied to a track, letting Last.fm correct
+    /// misspelled artist and track names. Any corrected names are returned
+    /// along with the tags.
+    pub async fn track_top_tags_autocorrected(
+        &self,
+        artist: &str,
+        track: &str,
+    ) -> Result<Corrected<Vec<Tag>>, errors::Error> {
+        let top_tags = self.fetch_track_top_tags(artist, track, true).await?;
+        let attr = top_tags.attr.unwrap_or_default();
+
+        Ok(Corrected::new(top_tags.tags)
+            .artist(artist, attr.artist)
+            .track(track, attr.track))
+    }
+
+    async fn fetch_track_top_tags(
+        &self,
+        artist: &str,
+        track: &str,
+        autocorrect: bool,
+    ) -> Result<InnerTopTags, errors::Error> {
+        require("artist", artist)?;
+        require("track", track)?;
+
+        let mut params = vec![("artist", artist), ("track", track)];
+        if autocorrect {
+            params.push(("autocorrect", "1"));
+        }
+        let url = self.build_as_uri("track.getTopTags", &params);
+
+        Ok(self.get::<TopTags>(url).await?.top_tags)
+    }
+
+    /// Search for tracks by name, optionally narrowed down to an artist.
+    pub async fn search_track(
+        &self,
+        track: &str,
+        artist: Option<&str>,
+        page: Option<u32>,
+        limit: Option<u32>,
+    ) -> Result<Paged<TrackMatch>, errors::Error> {
+        require("track", track)?;
+
+        let mut params = vec![("track", track.to_owned())];
+        params.extend(artist.map(|a| ("artist", a.to_owned())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        let url = self.build_as_uri("track.search", &params);
+
+        Ok(self
+            .get::<SearchResponse<TrackMatches>>(url)
+            .await?
+            .into_paged())
+    }
+
+    /// Mark a track as loved by the user with the session key `session_key`.
+    pub async fn love_track(
+        &self,
+        session_key: &str,
+        artist: &str,
+        track: &str,
+    ) -> Result<(), errors::Error> {
+        require("artist", artist)?;
+        require("track", track)?;
+
+        self.post_signed::<serde::de::IgnoredAny, _>(
+            "track.love",
+            session_key,
+            &[("artist", artist), ("track", track)],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove a track from the loved tracks of the user with the session key
+    /// `session_key`.
+    pub async fn unlove_track(
+        &self,
+        session_key: &str,
+        artist: &str,
+        track: &str,
+    ) -> Result<(), errors::Error> {
+        require("artist", artist)?;
+        require("track", track)?;
+
+        self.post_signed::<serde::de::IgnoredAny, _>(
+            "track.unlove",
+            session_key,
+            &[("artist", artist), ("track", track)],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Tag a track on behalf of the user with the session key `session_key`.
+    /// Fails with [`errors::Error::InvalidInput`] unless between 1 and 10
+    /// tags are given.
+    pub async fn add_track_tags(
+        &self,
+        session_key: &str,
+        artist: &str,
+        track: &str,
+        tags: &[&str],
+    ) -> Result<(), errors::Error> {
+        require("artist", artist)?;
+        require("track", track)?;
+
+        if tags.is_empty() || tags.len() > MAX_TAGS {
+            return Err(errors::Error::InvalidInput(format!(
+                "between 1 and {} tags must be given, not {}",
+                MAX_TAGS,
+                tags.len()
+            )));
+        }
+        for tag in tags {
+            require("tag", tag)?;
+        }
+
+        let tags = tags.join(",");
+        self.post_signed::<serde::de::IgnoredAny, _>(
+            "track.addTags",
+            session_key,
+            &[("artist", artist), ("track", track), ("tags", &tags)],
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove a tag the user with the session key `session_key` applied to a
+    /// track.
+    pub async fn remove_track_tag(
+        &self,
+        session_key: &str,
+        artist: &str,
+        track: &str,
+        tag: &str,
+    ) -> Result<(), errors::Error> {
+        require("artist", artist)?;
+        require("track", track)?;
+        require("tag", tag)?;
+
+        self.post_signed::<serde::de::IgnoredAny, _>(
+            "track.removeTag",
+            session_key,
+            &[("artist", artist), ("track", track), ("tag", tag)],
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::auth::sign_params;
+    use crate::lastfm::errors::ErrorCode;
+    use crate::lastfm::tests::{mock_client, signing_client};
+    use serde_json::json;
+    use wiremock::matchers::{
+        body_string_contains, method, path, query_param, query_param_is_missing,
+    };
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn deserialize_similar_tracks() {
+        let json = json!({"similartracks": {
+            "track": [{
+                "name": "Strong Enough",
+                "playcount": 670120,
+                "mbid": "39473218-db80-4db2-9623-690b79b94e04",
+                "match": 1.0,
+                "artist": {
+                    "name": "Cher",
+                    "mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818"
+                }
+            }]
+        }});
+
+        let tracks: SimilarTracks = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            tracks,
+            SimilarTracks {
+                similar_tracks: InnerSimilarTracks {
+                    tracks: vec![SimilarTrack {
+                        name: "Strong Enough".into(),
+                        artist: Artist {
+                            name: "Cher".into(),
+                            images: Default::default(),
+                            url: None,
+                        },
+                        match_score: 1.0,
+                        playcount: Some(670120),
+                        mbid: Some("39473218-db80-4db2-9623-690b79b94e04".into()),
+                        images: Default::default(),
+                        url: None,
+                    }],
+                    attr: None,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_single_similar_track() {
+        let json = json!({"similartracks": {
+            "track": {
+                "name": "Strong Enough",
+                "match": 1.0,
+                "artist": {"name": "Cher"}
+            },
+            "@attr": {"artist": "Cher"}
+        }});
+
+        let tracks: SimilarTracks = serde_json::from_value(json).unwrap();
+        assert_eq!(tracks.similar_tracks.tracks.len(), 1);
+        assert_eq!(tracks.similar_tracks.tracks[0].name, "Strong Enough");
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_without_track_field() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {
+                    "@attr": {"artist": "Nobody"}
+                }})),
+            )
+            .mount(&server)
+            .await;
+
+        let tracks = mock_client(&server)
+            .similar_tracks("Nobody", "Nothing")
+            .await
+            .unwrap();
+        assert!(tracks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_against_mock_server() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/2.0"))
+            .and(query_param("method", "track.getsimilar"))
+            .and(query_param("artist", "Cher"))
+            .and(query_param("track", "Believe"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {
+                    "track": [{"name": "Strong Enough", "match": 1.0, "artist": {"name": "Cher"}}]
+                }})),
+            )
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+
+        let tracks = client.similar_tracks("Cher", "Believe").await.unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].name, "Strong Enough");
+        assert_eq!(tracks[0].artist.name, "Cher");
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_limited_sends_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("method", "track.getsimilar"))
+            .and(query_param("limit", "50"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+
+        let tracks = client
+            .similar_tracks_limited("Cher", "Believe", 50)
+            .await
+            .unwrap();
+        assert!(tracks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_autocorrect_only_when_requested() {
+        let server = MockServer::start().await;
+        let empty = json!({"similartracks": {"track": []}});
+        Mock::given(query_param("autocorrect", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&empty))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(query_param_is_missing("autocorrect"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&empty))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        client
+            .similar_tracks_autocorrected("cher", "beleive")
+            .await
+            .unwrap();
+        client.similar_tracks("Cher", "Believe").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn track_top_tags_autocorrect_only_when_requested() {
+        let server = MockServer::start().await;
+        let tags = json!({"toptags": {
+            "tag": [
+                {"count": 100, "name": "pop", "url": "https://www.last.fm/tag/pop"},
+                {"count": 34, "name": "dance", "url": "https://www.last.fm/tag/dance"}
+            ],
+            "@attr": {"artist": "Cher", "track": "Believe"}
+        }});
+        Mock::given(query_param("method", "track.getTopTags"))
+            .and(query_param("track", "beleive"))
+            .and(query_param("autocorrect", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&tags))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(query_param("method", "track.getTopTags"))
+            .and(query_param("track", "Believe"))
+            .and(query_param_is_missing("autocorrect"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&tags))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = mock_client(&server);
+        let corrected = client
+            .track_top_tags_autocorrected("Cher", "beleive")
+            .await
+            .unwrap();
+        let tags = client.track_top_tags("Cher", "Believe").await.unwrap();
+
+        assert_eq!(corrected.data, tags);
+        assert_eq!(corrected.track.as_deref(), Some("Believe"));
+        assert_eq!(corrected.artist, None);
+        assert_eq!(
+            tags,
+            vec![
+                Tag {
+                    name: "pop".into(),
+                    count: 100,
+                    reach: 0,
+                },
+                Tag {
+                    name: "dance".into(),
+                    count: 34,
+                    reach: 0,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_autocorrected_returns_corrected_names() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("artist", "chr"))
+            .and(query_param("autocorrect", "1"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {
+                    "track": [{"name": "Strong Enough", "match": 1.0, "artist": {"name": "Cher"}}],
+                    "@attr": {"artist": "Cher"}
+                }})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let res = mock_client(&server)
+            .similar_tracks_autocorrected("chr", "Believe")
+            .await
+            .unwrap();
+
+        assert_eq!(res.artist.as_deref(), Some("Cher"));
+        assert_eq!(res.track, None);
+        assert_eq!(res.data.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_by_mbid_sends_mbid_only() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "track.getsimilar"))
+            .and(query_param("mbid", "39473218-db80-4db2-9623-690b79b94e04"))
+            .and(query_param_is_missing("artist"))
+            .and(query_param_is_missing("track"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tracks = mock_client(&server)
+            .similar_tracks_by_mbid("39473218-db80-4db2-9623-690b79b94e04")
+            .await
+            .unwrap();
+        assert!(tracks.is_empty());
+    }
+
+    #[test]
+    fn deserialize_track_info() {
+        let json = json!({"track": {
+            "name": "Believe",
+            "mbid": "32ca187e-ee25-4f18-b7d0-3b6713f24635",
+            "url": "https://www.last.fm/music/Cher/_/Believe",
+            "duration": "240000",
+            "streamable": {"#text": "0", "fulltrack": "0"},
+            "listeners": "1187804",
+            "playcount": "10337392",
+            "artist": {
+                "name": "Cher",
+                "mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818",
+                "url": "https://www.last.fm/music/Cher"
+            },
+            "album": {
+                "artist": "Cher",
+                "title": "Believe",
+                "mbid": "63b3a8ca-26f2-4e2b-b867-647a6ec2bebd",
+                "url": "https://www.last.fm/music/Cher/Believe",
+                "image": [{"#text": "https://example.com/34s.png", "size": "small"}],
+                "@attr": {"position": "1"}
+            },
+            "toptags": {"tag": [
+                {"name": "pop", "url": "https://www.last.fm/tag/pop"},
+                {"name": "dance", "url": "https://www.last.fm/tag/dance"}
+            ]},
+            "wiki": {
+                "published": "27 Jul 2008, 15:44",
+                "summary": "\"Believe\" is the twenty-third studio album by Cher.",
+                "content": "\"Believe\" is the twenty-third studio album by Cher. It was released in 1998."
+            }
+        }});
+
+        let info: TrackInfoResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            info.track,
+            TrackInfo {
+                name: "Believe".into(),
+                mbid: Some("32ca187e-ee25-4f18-b7d0-3b6713f24635".into()),
+                url: Some("https://www.last.fm/music/Cher/_/Believe".into()),
+                duration: Some(240000),
+                listeners: 1187804,
+                playcount: 10337392,
+                artist: Artist {
+                    name: "Cher".into(),
+                    images: Default::default(),
+                    url: Some("https://www.last.fm/music/Cher".into()),
+                },
+                album: Some(AlbumRef {
+                    artist: "Cher".into(),
+                    title: "Believe".into(),
+                    mbid: Some("63b3a8ca-26f2-4e2b-b867-647a6ec2bebd".into()),
+                    url: Some("https://www.last.fm/music/Cher/Believe".into()),
+                    images: Images {
+                        small: Some("https://example.com/34s.png".into()),
+                        ..Default::default()
+                    },
+                }),
+                tags: vec!["pop".into(), "dance".into()],
+                wiki_summary: Some("\"Believe\" is the twenty-third studio album by Cher.".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_track_info_without_optional_fields() {
+        let json = json!({"track": {
+            "name": "Demo",
+            "duration": "0",
+            "listeners": "3",
+            "playcount": "12",
+            "artist": {"name": "Nobody"},
+            "toptags": {"tag": []}
+        }});
+
+        let info: TrackInfoResponse = serde_json::from_value(json).unwrap();
+        assert_eq!(info.track.duration, Some(0));
+        assert_eq!(info.track.album, None);
+        assert!(info.track.tags.is_empty());
+        assert_eq!(info.track.wiki_summary, None);
+    }
+
+    #[test]
+    fn deserialize_track_search() {
+        let json = json!({"results": {
+            "opensearch:Query": {"#text": "", "role": "request", "startPage": "1"},
+            "opensearch:totalResults": "51281",
+            "opensearch:startIndex": "0",
+            "opensearch:itemsPerPage": "2",
+            "trackmatches": {"track": [
+                {
+                    "name": "Believe",
+                    "artist": "Cher",
+                    "url": "https://www.last.fm/music/Cher/_/Believe",
+                    "streamable": "FIXME",
+                    "listeners": "1187804",
+                    "image": [],
+                    "mbid": "32ca187e-ee25-4f18-b7d0-3b6713f24635"
+                },
+                {
+                    "name": "Believer",
+                    "artist": "Imagine Dragons",
+                    "url": "https://www.last.fm/music/Imagine+Dragons/_/Believer",
+                    "streamable": "FIXME",
+                    "listeners": "1223251",
+                    "image": [],
+                    "mbid": ""
+                }
+            ]},
+            "@attr": {}
+        }});
+
+        let res: SearchResponse<TrackMatches> = serde_json::from_value(json).unwrap();
+        let res = res.into_paged();
+        assert_eq!(res.pagination.total, 51281);
+        assert_eq!(res.pagination.per_page, 2);
+        assert_eq!(res.pagination.page, 1);
+        assert_eq!(
+            res.items,
+            vec![
+                TrackMatch {
+                    name: "Believe".into(),
+                    artist: "Cher".into(),
+                    mbid: Some("32ca187e-ee25-4f18-b7d0-3b6713f24635".into()),
+                    listeners: 1187804,
+                },
+                TrackMatch {
+                    name: "Believer".into(),
+                    artist: "Imagine Dragons".into(),
+                    mbid: None,
+                    listeners: 1223251,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn search_track_single_result() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "track.search"))
+            .and(query_param("track", "Believe"))
+            .and(query_param("artist", "Cher"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"results": {
+                "opensearch:totalResults": "1",
+                "opensearch:startIndex": "0",
+                "opensearch:itemsPerPage": "30",
+                "trackmatches": {"track": [{
+                    "name": "Believe",
+                    "artist": "Cher",
+                    "listeners": "1187804"
+                }]}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let res = mock_client(&server)
+            .search_track("Believe", Some("Cher"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(res.pagination.total, 1);
+        assert_eq!(
+            res.items,
+            vec![TrackMatch {
+                name: "Believe".into(),
+                artist: "Cher".into(),
+                mbid: None,
+                listeners: 1187804,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn love_track_posts_signed_params() {
+        let server = MockServer::start().await;
+        let sig = sign_params(
+            &[
+                ("method", "track.love"),
+                ("api_key", "key"),
+                ("artist", "Cher"),
+                ("track", "Believe"),
+                ("sk", "session"),
+            ],
+            "secret",
+        );
+        Mock::given(method("POST"))
+            .and(body_string_contains("method=track.love&"))
+            .and(body_string_contains("artist=Cher"))
+            .and(body_string_contains("track=Believe"))
+            .and(body_string_contains("sk=session"))
+            .and(body_string_contains(format!("api_sig={}", sig)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        signing_client(&server)
+            .love_track("session", "Cher", "Believe")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn unlove_track_posts_signed_params() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(body_string_contains("method=track.unlove&"))
+            .and(body_string_contains("sk=session"))
+            .and(body_string_contains("api_sig="))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        signing_client(&server)
+            .unlove_track("session", "Cher", "Believe")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn add_track_tags_posts_joined_tags() {
+        let server = MockServer::start().await;
+        let sig = sign_params(
+            &[
+                ("method", "track.addTags"),
+                ("api_key", "key"),
+                ("artist", "Cher"),
+                ("track", "Believe"),
+                ("tags", "pop,dance,female vocalists"),
+                ("sk", "session"),
+            ],
+            "secret",
+        );
+        Mock::given(method("POST"))
+            .and(body_string_contains("method=track.addTags&"))
+            .and(body_string_contains("tags=pop%2Cdance%2Cfemale+vocalists"))
+            .and(body_string_contains(format!("api_sig={}", sig)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        signing_client(&server)
+            .add_track_tags(
+                "session",
+                "Cher",
+                "Believe",
+                &["pop", "dance", "female vocalists"],
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn add_track_tags_rejects_too_many_tags() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = signing_client(&server);
+
+        let tags = ["pop"; 11];
+        let err = client
+            .add_track_tags("session", "Cher", "Believe", &tags)
+            .await;
+        assert!(matches!(err, Err(errors::Error::InvalidInput(m)) if m.contains("11")));
+
+        let err = client
+            .add_track_tags("session", "Cher", "Believe", &[])
+            .await;
+        assert!(matches!(err, Err(errors::Error::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn remove_track_tag_posts_signed_params() {
+        let server = MockServer::start().await;
+        let sig = sign_params(
+            &[
+                ("method", "track.removeTag"),
+                ("api_key", "key"),
+                ("artist", "Cher"),
+                ("track", "Believe"),
+                ("tag", "dance"),
+                ("sk", "session"),
+            ],
+            "secret",
+        );
+        Mock::given(method("POST"))
+            .and(body_string_contains("method=track.removeTag&"))
+            .and(body_string_contains("tag=dance"))
+            .and(body_string_contains(format!("api_sig={}", sig)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        signing_client(&server)
+            .remove_track_tag("session", "Cher", "Believe", "dance")
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn love_track_with_invalid_session() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": 9,
+                "message": "Invalid session key - Please re-authenticate"
+            })))
+            .mount(&server)
+            .await;
+
+        let err = signing_client(&server)
+            .love_track("expired", "Cher", "Believe")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, errors::Error::Api(e) if e.error == ErrorCode::InvalidSessionKey));
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_rejects_empty_input() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = mock_client(&server);
+
+        let err = client.similar_tracks("", "Believe").await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("artist")));
+
+        let err = client.similar_tracks("Cher", "  ").await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("track")));
+
+        let err = client.similar_tracks_limited(" ", "Believe", 5).await;
+        assert!(matches!(err, Err(errors::Error::InvalidInput(_))));
+
+        let err = client.similar_tracks_autocorrected("Cher", "").await;
+        assert!(matches!(err, Err(errors::Error::InvalidInput(_))));
+
+        let err = client.similar_tracks_by_mbid("").await;
+        assert!(matches!(err, Err(errors::Error::InvalidInput(_))));
+
+        let err = client.search_track(" ", Some("Cher"), None, None).await;
+        assert!(matches!(err, Err(errors::Error::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_raw_keeps_unmodelled_fields() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("method", "track.getsimilar"))
+            .and(query_param("artist", "Cher"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {
+                    "track": [{
+                        "name": "Strong Enough",
+                        "match": 1.0,
+                        "duration": 223,
+                        "artist": {"name": "Cher"}
+                    }],
+                    "@attr": {"artist": "Cher"}
+                }})),
+            )
+            .mount(&server)
+            .await;
+
+        let raw = mock_client(&server)
+            .similar_tracks_raw("Cher", "Believe")
+            .await
+            .unwrap();
+
+        assert_eq!(raw["similartracks"]["@attr"]["artist"], "Cher");
+        assert_eq!(raw["similartracks"]["track"][0]["duration"], 223);
+    }
+
+    #[tokio::test]
+    async fn raw_reports_api_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": 6,
+                "message": "Track not found"
+            })))
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server)
+            .raw("track.getInfo", &[("artist", "Cher"), ("track", "Nope")])
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, errors::Error::Api(e) if e.error == ErrorCode::InvalidParameters));
+    }
+
+    #[test]
+    fn similar_tracks_dedup_in_sets() {
+        let track = SimilarTrack {
+            name: "Strong Enough".into(),
+            artist: Artist {
+                name: "Cher".into(),
+                images: Default::default(),
+                url: None,
+            },
+            match_score: 0.5,
+            playcount: Some(670120),
+            mbid: None,
+            images: Default::default(),
+            url: None,
+        };
+        let rescored = SimilarTrack {
+            match_score: 0.25,
+            ..track.clone()
+        };
+
+        let other = SimilarTrack {
+            name: "Believe".into(),
+            ..track.clone()
+        };
+
+        let set: std::collections::HashSet<SimilarTrack> = vec![
+            track.clone(),
+            rescored.clone(),
+            track.clone(),
+            other.clone(),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(&rescored));
+        assert!(set.contains(&other));
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_many_preserves_seed_order() {
+        let server = MockServer::start().await;
+        for (track, similar) in &[("Believe", "Strong Enough"), ("Vogue", "Frozen")] {
+            Mock::given(method("GET"))
+                .and(query_param("track", *track))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_json(json!({"similartracks": {
+                        "track": [{"name": similar, "match": 1.0, "artist": {"name": "Cher"}}]
+                    }})),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(query_param("track", "Nope"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "error": 6,
+                "message": "Track not found"
+            })))
+            .mount(&server)
+            .await;
+
+        let seeds = vec![
+            ("Cher".to_owned(), "Believe".to_owned()),
+            ("Cher".to_owned(), "Nope".to_owned()),
+            ("Madonna".to_owned(), "Vogue".to_owned()),
+        ];
+        let results = mock_client(&server).similar_tracks_many(&seeds, 2).await;
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap()[0].name, "Strong Enough");
+        assert!(results[1].is_err());
+        assert_eq!(results[2].as_ref().unwrap()[0].name, "Frozen");
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_many_bounds_concurrency() {
+        use crate::lastfm::{ClientBuilder, HttpRequest, HttpResponse};
+        use futures::future::BoxFuture;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Records the most requests in flight at once.
+        #[derive(Default)]
+        struct Concurrency {
+            in_flight: AtomicUsize,
+            max: AtomicUsize,
+        }
+
+        impl HttpClient for Concurrency {
+            fn request(
+                &self,
+                _: HttpRequest,
+            ) -> BoxFuture<'_, Result<HttpResponse, errors::Error>> {
+                Box::pin(async move {
+                    let n = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    self.max.fetch_max(n, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                    self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    Ok(HttpResponse {
+                        status: reqwest::StatusCode::OK,
+                        headers: Default::default(),
+                        body: r#"{"similartracks": {"track": []}}"#.into(),
+                    })
+                })
+            }
+        }
+
+        let client = ClientBuilder::new("key").build_with(Concurrency::default());
+        let seeds: Vec<_> = (0..10)
+            .map(|i| ("Cher".to_owned(), format!("Track {}", i)))
+            .collect();
+
+        let results = client.similar_tracks_many(&seeds, 3).await;
+
+        assert_eq!(results.len(), 10);
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(client.http.max.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_with_mbid_and_limit() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("mbid", "32ca187e-ee25-4f18-b7d0-3b6713f24635"))
+            .and(query_param("limit", "5"))
+            .and(query_param_is_missing("artist"))
+            .and(query_param_is_missing("autocorrect"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let opts = SimilarTracksOptions::new()
+            .artist("Cher")
+            .mbid("32ca187e-ee25-4f18-b7d0-3b6713f24635")
+            .limit(5);
+        mock_client(&server)
+            .similar_tracks_with(&opts)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_with_autocorrect() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(query_param("artist", "cher"))
+            .and(query_param("track", "beleive"))
+            .and(query_param("autocorrect", "1"))
+            .and(query_param_is_missing("limit"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"similartracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let opts = SimilarTracksOptions::new()
+            .artist("cher")
+            .track("beleive")
+            .autocorrect(true);
+        mock_client(&server)
+            .similar_tracks_with(&opts)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn similar_tracks_with_requires_a_seed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = mock_client(&server);
+
+        let err = client
+            .similar_tracks_with(&SimilarTracksOptions::new().artist("Cher").limit(5))
+            .await;
+        assert!(matches!(err, Err(errors::Error::InvalidInput(_))));
+
+        let err = client
+            .similar_tracks_with(&SimilarTracksOptions::new())
+            .await;
+        assert!(matches!(err, Err(errors::Error::InvalidInput(_))));
+    }
+
+    #[tokio::test]
+    async fn love_track_rejects_empty_input() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = signing_client(&server);
+
+        let err = client.love_track("sk", "", "Believe").await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("artist")));
+
+        let err = client.unlove_track("sk", "Cher", " ").await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("track")));
+
+        let err = client.love_track("", "Cher", "Believe").await.unwrap_err();
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("session_key")));
+    }
+}
@@ -0,0 +1,1174 @@
+use serde::Deserialize;
+
+use super::{de, errors, require, Artist, Client, HttpClient, Paged, Pages, Pagination, Period};
+
+/// One of a user's most listened to artists, as returned by
+/// `user.getTopArtists`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct UserTopArtist {
+    pub name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    #[serde(deserialize_with = "de::number")]
+    pub playcount: u64,
+    /// Position of the artist in the user's chart, starting at 1
+    #[serde(rename = "@attr", deserialize_with = "de::rank")]
+    pub rank: u32,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct TopArtists {
+    #[serde(rename = "topartists")]
+    top_artists: InnerTopArtists,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct InnerTopArtists {
+    #[serde(rename = "artist", default, deserialize_with = "de::one_or_many")]
+    artists: Vec<UserTopArtist>,
+}
+
+/// One of a user's most listened to tracks, as returned by
+/// `user.getTopTracks`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct UserTopTrack {
+    pub name: String,
+    pub artist: Artist,
+    #[serde(deserialize_with = "de::number")]
+    pub playcount: u64,
+    /// Length of the track in seconds, if known
+    #[serde(default, deserialize_with = "de::option_number")]
+    pub duration: Option<u64>,
+    /// Position of the track in the user's chart, starting at 1
+    #[serde(rename = "@attr", deserialize_with = "de::rank")]
+    pub rank: u32,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct TopTracks {
+    #[serde(rename = "toptracks")]
+    top_tracks: InnerTopTracks,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct InnerTopTracks {
+    #[serde(rename = "track", default, deserialize_with = "de::one_or_many")]
+    tracks: Vec<UserTopTrack>,
+}
+
+/// A track on a user's weekly chart, as returned by
+/// `user.getWeeklyTrackChart`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct WeeklyTrack {
+    pub name: String,
+    #[serde(deserialize_with = "de::text")]
+    pub artist: String,
+    /// How often the user listened to the track that week
+    #[serde(deserialize_with = "de::number")]
+    pub playcount: u64,
+    /// Position of the track in the chart, starting at 1
+    #[serde(rename = "@attr", deserialize_with = "de::rank")]
+    pub rank: u32,
+}
+
+#[derive(Deserialize, Debug)]
+struct WeeklyTrackChart {
+    #[serde(rename = "weeklytrackchart")]
+    chart: InnerWeeklyTrackChart,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerWeeklyTrackChart {
+    #[serde(rename = "track", default, deserialize_with = "de::one_or_many")]
+    tracks: Vec<WeeklyTrack>,
+}
+
+/// A week for which a user's weekly charts are available, as returned by
+/// `user.getWeeklyChartList`. Either bound can be passed to the weekly chart
+/// methods as `from` and `to`.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChartWeek {
+    /// The start of the week, as seconds since the Unix epoch
+    #[serde(deserialize_with = "de::number")]
+    pub from: u64,
+    /// The end of the week, as seconds since the Unix epoch
+    #[serde(deserialize_with = "de::number")]
+    pub to: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct WeeklyChartList {
+    #[serde(rename = "weeklychartlist")]
+    list: InnerWeeklyChartList,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerWeeklyChartList {
+    #[serde(rename = "chart", default, deserialize_with = "de::one_or_many")]
+    weeks: Vec<ChartWeek>,
+}
+
+/// A track a user listened to, as returned by `user.getRecentTracks`.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(from = "RawRecentTrack")]
+pub struct RecentTrack {
+    pub name: String,
+    pub artist: String,
+    pub album: Option<String>,
+    /// When the track was scrobbled, as seconds since the Unix epoch. The
+    /// track currently playing has not been scrobbled yet.
+    pub timestamp: Option<u64>,
+    pub now_playing: bool,
+    /// Whether the user loved the track. This is only reported when fetched
+    /// with [`RecentTracksOptions::extended`], and is otherwise false.
+    pub loved: bool,
+}
+
+#[derive(Deserialize)]
+struct RawRecentTrack {
+    name: String,
+    #[serde(deserialize_with = "de::artist_name")]
+    artist: String,
+    #[serde(default, deserialize_with = "de::option_text")]
+    album: Option<String>,
+    #[serde(default, deserialize_with = "de::option_timestamp")]
+    date: Option<u64>,
+    #[serde(rename = "@attr")]
+    attr: Option<RecentTrackAttr>,
+    #[serde(default, deserialize_with = "de::option_number")]
+    loved: Option<u8>,
+}
+
+#[derive(Deserialize)]
+struct RecentTrackAttr {
+    #[serde(default)]
+    nowplaying: String,
+}
+
+impl From<RawRecentTrack> for RecentTrack {
+    fn from(raw: RawRecentTrack) -> Self {
+        RecentTrack {
+            name: raw.name,
+            artist: raw.artist,
+            album: raw.album,
+            timestamp: raw.date,
+            now_playing: raw.attr.is_some_and(|a| a.nowplaying == "true"),
+            loved: raw.loved == Some(1),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct RecentTracks {
+    #[serde(rename = "recenttracks")]
+    recent_tracks: InnerRecentTracks,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct InnerRecentTracks {
+    #[serde(rename = "track", default, deserialize_with = "de::one_or_many")]
+    tracks: Vec<RecentTrack>,
+}
+
+/// Options for [`Client::user_recent_tracks_with`].
+///
+/// ```
+/// use playlie::lastfm::RecentTracksOptions;
+///
+/// let opts = RecentTracksOptions::new()
+///     .from(1612699200)
+///     .to(1613304000)
+///     .extended(true);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RecentTracksOptions {
+    limit: Option<u32>,
+    from: Option<u64>,
+    to: Option<u64>,
+    extended: bool,
+}
+
+impl RecentTracksOptions {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Fetch at most `limit` tracks.
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Only fetch tracks scrobbled at or after the Unix timestamp `from`.
+    pub fn from(mut self, from: u64) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    /// Only fetch tracks scrobbled at or before the Unix timestamp `to`.
+    pub fn to(mut self, to: u64) -> Self {
+        self.to = Some(to);
+        self
+    }
+
+    /// Report whether the user loved each track. Disabled by default.
+    pub fn extended(mut self, extended: bool) -> Self {
+        self.extended = extended;
+        self
+    }
+}
+
+/// A track a user loved, as returned by `user.getLovedTracks`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct LovedTrack {
+    pub name: String,
+    pub artist: Artist,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    /// When the track was loved, as seconds since the Unix epoch
+    #[serde(deserialize_with = "de::timestamp")]
+    pub date: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct LovedTracks {
+    #[serde(rename = "lovedtracks")]
+    loved_tracks: InnerLovedTracks,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerLovedTracks {
+    #[serde(rename = "track", default, deserialize_with = "de::one_or_many")]
+    tracks: Vec<LovedTrack>,
+    #[serde(rename = "@attr")]
+    attr: Pagination,
+}
+
+/// A user's profile, as returned by `user.getInfo`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct UserInfo {
+    pub name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub realname: Option<String>,
+    #[serde(deserialize_with = "de::number")]
+    pub playcount: u64,
+    /// When the user signed up, as seconds since the Unix epoch
+    #[serde(deserialize_with = "de::timestamp")]
+    pub registered: u64,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub country: Option<String>,
+    pub url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct User {
+    user: UserInfo,
+}
+
+/// A friend of a user, as returned by `user.getFriends`.
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Friend {
+    pub name: String,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub realname: Option<String>,
+    pub url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Friends {
+    friends: InnerFriends,
+}
+
+#[derive(Deserialize, Debug)]
+struct InnerFriends {
+    #[serde(rename = "user", default, deserialize_with = "de::one_or_many")]
+    users: Vec<Friend>,
+    #[serde(rename = "@attr")]
+    attr: Pagination,
+}
+
+/// The kind of item a user applied a tag to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaggingType {
+    Artist,
+    Album,
+    Track,
+}
+
+impl TaggingType {
+    /// The value of the `taggingtype` parameter expected by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaggingType::Artist => "artist",
+            TaggingType::Album => "album",
+            TaggingType::Track => "track",
+        }
+    }
+}
+
+impl std::fmt::Display for TaggingType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An item a user applied a tag to, as returned by `user.getPersonalTags`.
+/// The variant matches the [`TaggingType`] requested.
+#[derive(Debug, PartialEq)]
+pub enum TaggedItem {
+    Artist(Artist),
+    Album(TaggedAlbum),
+    Track(TaggedTrack),
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct TaggedAlbum {
+    pub name: String,
+    pub artist: Artist,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct TaggedTrack {
+    pub name: String,
+    pub artist: Artist,
+    #[serde(default, deserialize_with = "de::option_from_str")]
+    pub mbid: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PersonalTags {
+    taggings: Taggings,
+}
+
+/// Only the list matching the requested tagging type is present.
+#[derive(Deserialize, Debug)]
+struct Taggings {
+    artists: Option<TaggedArtists>,
+    albums: Option<TaggedAlbums>,
+    tracks: Option<TaggedTracks>,
+    #[serde(rename = "@attr")]
+    attr: Pagination,
+}
+
+#[derive(Deserialize, Debug)]
+struct TaggedArtists {
+    #[serde(default, deserialize_with = "de::one_or_many")]
+    artist: Vec<Artist>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TaggedAlbums {
+    #[serde(default, deserialize_with = "de::one_or_many")]
+    album: Vec<TaggedAlbum>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TaggedTracks {
+    #[serde(default, deserialize_with = "de::one_or_many")]
+    track: Vec<TaggedTrack>,
+}
+
+impl Taggings {
+    fn into_paged(self) -> Paged<TaggedItem> {
+        let artists = self.artists.into_iter().flat_map(|a| a.artist);
+        let albums = self.albums.into_iter().flat_map(|a| a.album);
+        let tracks = self.tracks.into_iter().flat_map(|t| t.track);
+        let items = artists
+            .map(TaggedItem::Artist)
+            .chain(albums.map(TaggedItem::Album))
+            .chain(tracks.map(TaggedItem::Track))
+            .collect();
+
+        Paged {
+            items,
+            pagination: self.attr,
+        }
+    }
+}
+
+impl<H: HttpClient> Client<H> {
+    /// Fetch the artists a user listened to most over the given period,
+    /// ordered by rank.
+    pub async fn user_top_artists(
+        &self,
+        user: &str,
+        period: Period,
+        limit: Option<u32>,
+    ) -> Result<Vec<UserTopArtist>, errors::Error> {
+        require("user", user)?;
+
+        let mut params = vec![("user", user.to_owned()), ("period", period.to_string())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        let url = self.build_as_uri("user.getTopArtists", &params);
+
+        Ok(self.get::<TopArtists>(url).await?.top_artists.artists)
+    }
+
+    /// Fetch the tracks a user listened to most over the given period,
+    /// ordered by rank.
+    pub async fn user_top_tracks(
+        &self,
+        user: &str,
+        period: Period,
+        limit: Option<u32>,
+    ) -> Result<Vec<UserTopTrack>, errors::Error> {
+        require("user", user)?;
+
+        let mut params = vec![("user", user.to_owned()), ("period", period.to_string())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        let url = self.build_as_uri("user.getTopTracks", &params);
+
+        Ok(self.get::<TopTracks>(url).await?.top_tracks.tracks)
+    }
+
+    /// Fetch the tracks a user listened to most recently, including the track
+    /// they are listening to now.
+    pub async fn user_recent_tracks(
+        &self,
+        user: &str,
+        limit: Option<u32>,
+    ) -> Result<Vec<RecentTrack>, errors::Error> {
+        let mut opts = RecentTracksOptions::new();
+        opts.limit = limit;
+
+        self.user_recent_tracks_with(user, &opts).await
+    }
+
+    /// Fetch the tracks a user listened to most recently, as configured by
+    /// `opts`.
+    pub async fn user_recent_tracks_with(
+        &self,
+        user: &str,
+        opts: &RecentTracksOptions,
+    ) -> Result<Vec<RecentTrack>, errors::Error> {
+        require("user", user)?;
+
+        let mut params = vec![("user", user.to_owned())];
+        params.extend(self.limit(opts.limit).map(|l| ("limit", l.to_string())));
+        params.extend(opts.from.map(|f| ("from", f.to_string())));
+        params.extend(opts.to.map(|t| ("to", t.to_string())));
+        if opts.extended {
+            params.push(("extended", "1".to_owned()));
+        }
+        let url = self.build_as_uri("user.getRecentTracks", &params);
+
+        Ok(self.get::<RecentTracks>(url).await?.recent_tracks.tracks)
+    }
+
+    /// Fetch the tracks a user listened to during the week between the Unix
+    /// timestamps `from` and `to`, ordered by rank. Without them, the most
+    /// recent week is fetched.
+    pub async fn user_weekly_track_chart(
+        &self,
+        user: &str,
+        from: Option<u64>,
+        to: Option<u64>,
+    ) -> Result<Vec<WeeklyTrack>, errors::Error> {
+        require("user", user)?;
+
+        let mut params = vec![("user", user.to_owned())];
+        params.extend(from.map(|f| ("from", f.to_string())));
+        params.extend(to.map(|t| ("to", t.to_string())));
+        let url = self.build_as_uri("user.getWeeklyTrackChart", &params);
+
+        Ok(self.get::<WeeklyTrackChart>(url).await?.chart.tracks)
+    }
+
+    /// Fetch the weeks for which a user's weekly charts are available, oldest
+    /// first.
+    pub async fn user_weekly_chart_list(
+        &self,
+        user: &str,
+    ) -> Result<Vec<ChartWeek>, errors::Error> {
+        require("user", user)?;
+
+        let url = self.build_as_uri("user.getWeeklyChartList", &[("user", user)]);
+
+        Ok(self.get::<WeeklyChartList>(url).await?.list.weeks)
+    }
+
+    /// Fetch a page of the tracks a user has loved, most recent first.
+    pub async fn user_loved_tracks(
+        &self,
+        user: &str,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<Paged<LovedTrack>, errors::Error> {
+        require("user", user)?;
+
+        let mut params = vec![("user", user.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        let url = self.build_as_uri("user.getLovedTracks", &params);
+
+        let loved = self.get::<LovedTracks>(url).await?.loved_tracks;
+
+        Ok(Paged {
+            items: loved.tracks,
+            pagination: loved.attr,
+        })
+    }
+
+    /// Fetch a user's profile. Fails with
+    /// [`ErrorCode::InvalidParameters`](errors::ErrorCode::InvalidParameters)
+    /// if the user does not exist.
+    pub async fn user_info(&self, user: &str) -> Result<UserInfo, errors::Error> {
+        require("user", user)?;
+
+        let url = self.build_as_uri("user.getInfo", &[("user", user)]);
+
+        Ok(self.get::<User>(url).await?.user)
+    }
+
+    /// Fetch a page of a user's friends. A user without friends yields an
+    /// empty page with a `total` of 0.
+    pub async fn user_friends(
+        &self,
+        user: &str,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<Paged<Friend>, errors::Error> {
+        require("user", user)?;
+
+        let mut params = vec![("user", user.to_owned())];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        let url = self.build_as_uri("user.getFriends", &params);
+
+        let friends = self.get::<Friends>(url).await?.friends;
+
+        Ok(Paged {
+            items: friends.users,
+            pagination: friends.attr,
+        })
+    }
+
+    /// Fetch a page of the artists, albums or tracks a user applied `tag` to.
+    pub async fn user_personal_tags(
+        &self,
+        user: &str,
+        tag: &str,
+        tagging_type: TaggingType,
+        limit: Option<u32>,
+        page: Option<u32>,
+    ) -> Result<Paged<TaggedItem>, errors::Error> {
+        require("user", user)?;
+        require("tag", tag)?;
+
+        let mut params = vec![
+            ("user", user.to_owned()),
+            ("tag", tag.to_owned()),
+            ("taggingtype", tagging_type.to_string()),
+        ];
+        params.extend(self.limit(limit).map(|l| ("limit", l.to_string())));
+        params.extend(self.page(page).map(|p| ("page", p.to_string())));
+        let url = self.build_as_uri("user.getPersonalTags", &params);
+
+        Ok(self.get::<PersonalTags>(url).await?.taggings.into_paged())
+    }
+
+    /// Stream all of the tracks a user has loved, most recent first.
+    pub fn user_loved_tracks_pages<'a>(&'a self, user: &'a str) -> Pages<'a, LovedTrack> {
+        Pages::new(move |page| self.user_loved_tracks(user, None, Some(page)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::tests::mock_client;
+    use serde_json::json;
+    use wiremock::matchers::{query_param, query_param_is_missing};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn deserialize_top_artists() {
+        let json = json!({"topartists": {
+            "artist": [{
+                "streamable": "0",
+                "image": [],
+                "mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818",
+                "url": "https://www.last.fm/music/Cher",
+                "playcount": "1024",
+                "@attr": {"rank": "1"},
+                "name": "Cher"
+            }, {
+                "streamable": "0",
+                "image": [],
+                "mbid": "",
+                "url": "https://www.last.fm/music/Madonna",
+                "playcount": "512",
+                "@attr": {"rank": "2"},
+                "name": "Madonna"
+            }],
+            "@attr": {"page": "1", "total": "2", "user": "sebnow", "perPage": "50", "totalPages": "1"}
+        }});
+
+        let artists: TopArtists = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            artists.top_artists.artists,
+            vec![
+                UserTopArtist {
+                    name: "Cher".into(),
+                    mbid: Some("bfcc6d75-a6a5-4bc6-8282-47aec8531818".into()),
+                    playcount: 1024,
+                    rank: 1,
+                },
+                UserTopArtist {
+                    name: "Madonna".into(),
+                    mbid: None,
+                    playcount: 512,
+                    rank: 2,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn user_top_artists_sends_period() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "user.getTopArtists"))
+            .and(query_param("user", "sebnow"))
+            .and(query_param("period", "3month"))
+            .and(query_param("limit", "10"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"topartists": {"artist": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let artists = mock_client(&server)
+            .user_top_artists("sebnow", Period::ThreeMonths, Some(10))
+            .await
+            .unwrap();
+        assert!(artists.is_empty());
+    }
+
+    #[test]
+    fn deserialize_top_tracks() {
+        let json = json!({"toptracks": {
+            "track": [{
+                "streamable": {"fulltrack": "0", "#text": "0"},
+                "mbid": "32ca187e-ee25-4f18-b7d0-3b6713f24635",
+                "name": "Believe",
+                "image": [],
+                "artist": {"url": "https://www.last.fm/music/Cher", "name": "Cher", "mbid": ""},
+                "url": "https://www.last.fm/music/Cher/_/Believe",
+                "duration": "240",
+                "@attr": {"rank": "1"},
+                "playcount": "78"
+            }, {
+                "streamable": {"fulltrack": "0", "#text": "0"},
+                "mbid": "",
+                "name": "Vogue",
+                "image": [],
+                "artist": {"url": "https://www.last.fm/music/Madonna", "name": "Madonna", "mbid": ""},
+                "url": "https://www.last.fm/music/Madonna/_/Vogue",
+                "duration": "0",
+                "@attr": {"rank": "2"},
+                "playcount": "41"
+            }],
+            "@attr": {"page": "1", "total": "2", "user": "sebnow", "perPage": "50", "totalPages": "1"}
+        }});
+
+        let tracks: TopTracks = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            tracks.top_tracks.tracks,
+            vec![
+                UserTopTrack {
+                    name: "Believe".into(),
+                    artist: Artist {
+                        name: "Cher".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Cher".into()),
+                    },
+                    playcount: 78,
+                    duration: Some(240),
+                    rank: 1,
+                },
+                UserTopTrack {
+                    name: "Vogue".into(),
+                    artist: Artist {
+                        name: "Madonna".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Madonna".into()),
+                    },
+                    playcount: 41,
+                    duration: Some(0),
+                    rank: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_single_top_track() {
+        let json = json!({"toptracks": {
+            "track": {
+                "name": "Believe",
+                "artist": {"name": "Cher"},
+                "duration": "240",
+                "@attr": {"rank": "1"},
+                "playcount": "78"
+            },
+            "@attr": {"page": "1", "total": "1", "user": "sebnow", "perPage": "50", "totalPages": "1"}
+        }});
+
+        let tracks: TopTracks = serde_json::from_value(json).unwrap();
+        assert_eq!(tracks.top_tracks.tracks.len(), 1);
+        assert_eq!(tracks.top_tracks.tracks[0].name, "Believe");
+        assert_eq!(tracks.top_tracks.tracks[0].rank, 1);
+    }
+
+    #[test]
+    fn deserialize_recent_tracks() {
+        let json = json!({"recenttracks": {
+            "track": [{
+                "artist": {"mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818", "#text": "Cher"},
+                "streamable": "0",
+                "image": [],
+                "mbid": "",
+                "album": {"mbid": "", "#text": "Believe"},
+                "name": "Believe",
+                "@attr": {"nowplaying": "true"},
+                "url": "https://www.last.fm/music/Cher/_/Believe"
+            }, {
+                "artist": {"mbid": "", "#text": "Madonna"},
+                "streamable": "0",
+                "image": [],
+                "mbid": "",
+                "album": {"mbid": "", "#text": ""},
+                "name": "Vogue",
+                "url": "https://www.last.fm/music/Madonna/_/Vogue",
+                "date": {"uts": "1613463960", "#text": "16 Feb 2021, 08:26"}
+            }],
+            "@attr": {"page": "1", "total": "2", "user": "sebnow", "perPage": "50", "totalPages": "1"}
+        }});
+
+        let tracks: RecentTracks = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            tracks.recent_tracks.tracks,
+            vec![
+                RecentTrack {
+                    name: "Believe".into(),
+                    artist: "Cher".into(),
+                    album: Some("Believe".into()),
+                    timestamp: None,
+                    now_playing: true,
+                    loved: false,
+                },
+                RecentTrack {
+                    name: "Vogue".into(),
+                    artist: "Madonna".into(),
+                    album: None,
+                    timestamp: Some(1613463960),
+                    now_playing: false,
+                    loved: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_loved_tracks() {
+        let json = json!({"lovedtracks": {
+            "track": [{
+                "artist": {"url": "https://www.last.fm/music/Cher", "name": "Cher", "mbid": ""},
+                "date": {"uts": "1613463960", "#text": "16 Feb 2021, 08:26"},
+                "mbid": "32ca187e-ee25-4f18-b7d0-3b6713f24635",
+                "url": "https://www.last.fm/music/Cher/_/Believe",
+                "name": "Believe",
+                "image": [],
+                "streamable": {"fulltrack": "0", "#text": "0"}
+            }],
+            "@attr": {"user": "sebnow", "totalPages": "3", "page": "2", "perPage": "1", "total": "3"}
+        }});
+
+        let loved: LovedTracks = serde_json::from_value(json).unwrap();
+        let loved = loved.loved_tracks;
+        assert_eq!(
+            Paged {
+                items: loved.tracks,
+                pagination: loved.attr,
+            },
+            Paged {
+                items: vec![LovedTrack {
+                    name: "Believe".into(),
+                    artist: Artist {
+                        name: "Cher".into(),
+                        images: Default::default(),
+                        url: Some("https://www.last.fm/music/Cher".into()),
+                    },
+                    mbid: Some("32ca187e-ee25-4f18-b7d0-3b6713f24635".into()),
+                    date: 1613463960,
+                }],
+                pagination: Pagination {
+                    page: 2,
+                    per_page: 1,
+                    total_pages: 3,
+                    total: 3,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_user_info() {
+        let json = json!({"user": {
+            "name": "RJ",
+            "age": "0",
+            "subscriber": "1",
+            "realname": "Richard Jones ",
+            "bootstrap": "0",
+            "playcount": "150316",
+            "artist_count": "12749",
+            "playlists": "0",
+            "track_count": "57066",
+            "album_count": "26658",
+            "image": [{"size": "small", "#text": "https://lastfm.freetls.fastly.net/i/u/34s/1.png"}],
+            "registered": {"unixtime": "1037793040", "#text": 1037793040},
+            "country": "United Kingdom",
+            "gender": "n",
+            "url": "https://www.last.fm/user/RJ",
+            "type": "alum"
+        }});
+
+        let user: User = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            user.user,
+            UserInfo {
+                name: "RJ".into(),
+                realname: Some("Richard Jones ".into()),
+                playcount: 150316,
+                registered: 1037793040,
+                country: Some("United Kingdom".into()),
+                url: "https://www.last.fm/user/RJ".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn deserialize_user_info_without_optional_fields() {
+        let json = json!({"user": {
+            "name": "sebnow",
+            "realname": "",
+            "playcount": "0",
+            "registered": {"unixtime": "1200000000", "#text": 1200000000},
+            "country": "",
+            "url": "https://www.last.fm/user/sebnow"
+        }});
+
+        let user: User = serde_json::from_value(json).unwrap();
+        assert_eq!(user.user.realname, None);
+        assert_eq!(user.user.country, None);
+    }
+
+    #[test]
+    fn deserialize_friends() {
+        let json = json!({"friends": {
+            "user": [{
+                "name": "RJ",
+                "realname": "Richard Jones",
+                "url": "https://www.last.fm/user/RJ",
+                "country": "United Kingdom",
+                "playcount": "150316",
+                "registered": {"unixtime": "1037793040", "#text": "2002-11-20 11:50"}
+            }, {
+                "name": "sebnow",
+                "realname": "",
+                "url": "https://www.last.fm/user/sebnow"
+            }],
+            "@attr": {"user": "test", "totalPages": "1", "page": "1", "perPage": "50", "total": "2"}
+        }});
+
+        let friends: Friends = serde_json::from_value(json).unwrap();
+        let friends = friends.friends;
+        assert_eq!(friends.attr.total, 2);
+        assert_eq!(
+            friends.users,
+            vec![
+                Friend {
+                    name: "RJ".into(),
+                    realname: Some("Richard Jones".into()),
+                    url: "https://www.last.fm/user/RJ".into(),
+                },
+                Friend {
+                    name: "sebnow".into(),
+                    realname: None,
+                    url: "https://www.last.fm/user/sebnow".into(),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn user_without_friends_returns_empty_page() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "user.getFriends"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"friends": {
+                "user": [],
+                "@attr": {"user": "loner", "totalPages": "0", "page": "1", "perPage": "50", "total": "0"}
+            }})))
+            .mount(&server)
+            .await;
+
+        let friends = mock_client(&server)
+            .user_friends("loner", None, None)
+            .await
+            .unwrap();
+
+        assert!(friends.items.is_empty());
+        assert_eq!(friends.pagination.total, 0);
+    }
+
+    #[test]
+    fn deserialize_weekly_chart_list() {
+        let json = json!({"weeklychartlist": {
+            "chart": [
+                {"#text": "", "from": "1108296000", "to": "1108900800"},
+                {"#text": "", "from": "1108900800", "to": "1109505600"}
+            ],
+            "@attr": {"user": "RJ"}
+        }});
+
+        let list: WeeklyChartList = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            list.list.weeks,
+            vec![
+                ChartWeek {
+                    from: 1108296000,
+                    to: 1108900800,
+                },
+                ChartWeek {
+                    from: 1108900800,
+                    to: 1109505600,
+                },
+            ]
+        );
+    }
+
+    fn weekly_track_chart() -> serde_json::Value {
+        json!({"weeklytrackchart": {
+            "track": [{
+                "artist": {"mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818", "#text": "Cher"},
+                "@attr": {"rank": "1"},
+                "image": [],
+                "mbid": "",
+                "url": "https://www.last.fm/music/Cher/_/Believe",
+                "name": "Believe",
+                "playcount": "12"
+            }],
+            "@attr": {"from": "1612699200", "user": "sebnow", "to": "1613304000"}
+        }})
+    }
+
+    #[tokio::test]
+    async fn user_weekly_track_chart_defaults_to_latest_week() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "user.getWeeklyTrackChart"))
+            .and(query_param("user", "sebnow"))
+            .and(query_param_is_missing("from"))
+            .and(query_param_is_missing("to"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(weekly_track_chart()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tracks = mock_client(&server)
+            .user_weekly_track_chart("sebnow", None, None)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tracks,
+            vec![WeeklyTrack {
+                name: "Believe".into(),
+                artist: "Cher".into(),
+                playcount: 12,
+                rank: 1,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn user_weekly_track_chart_sends_range() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "user.getWeeklyTrackChart"))
+            .and(query_param("from", "1612699200"))
+            .and(query_param("to", "1613304000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(weekly_track_chart()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tracks = mock_client(&server)
+            .user_weekly_track_chart("sebnow", Some(1612699200), Some(1613304000))
+            .await
+            .unwrap();
+
+        assert_eq!(tracks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn user_recent_tracks_sends_range_and_extended() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "user.getRecentTracks"))
+            .and(query_param("from", "1612699200"))
+            .and(query_param("to", "1613304000"))
+            .and(query_param("extended", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"recenttracks": {
+                "track": [{
+                    "artist": {
+                        "url": "https://www.last.fm/music/Cher",
+                        "name": "Cher",
+                        "image": [],
+                        "mbid": "bfcc6d75-a6a5-4bc6-8282-47aec8531818"
+                    },
+                    "loved": "1",
+                    "album": {"mbid": "", "#text": "Believe"},
+                    "name": "Believe",
+                    "date": {"uts": "1613000000", "#text": "10 Feb 2021, 23:33"}
+                }, {
+                    "artist": {"url": "https://www.last.fm/music/Madonna", "name": "Madonna"},
+                    "loved": "0",
+                    "name": "Vogue",
+                    "@attr": {"nowplaying": "true"}
+                }],
+                "@attr": {"page": "1", "total": "2", "user": "sebnow", "perPage": "50", "totalPages": "1"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let opts = RecentTracksOptions::new()
+            .from(1612699200)
+            .to(1613304000)
+            .extended(true);
+        let tracks = mock_client(&server)
+            .user_recent_tracks_with("sebnow", &opts)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tracks,
+            vec![
+                RecentTrack {
+                    name: "Believe".into(),
+                    artist: "Cher".into(),
+                    album: Some("Believe".into()),
+                    timestamp: Some(1613000000),
+                    now_playing: false,
+                    loved: true,
+                },
+                RecentTrack {
+                    name: "Vogue".into(),
+                    artist: "Madonna".into(),
+                    album: None,
+                    timestamp: None,
+                    now_playing: true,
+                    loved: false,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn user_recent_tracks_omits_unset_options() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "user.getRecentTracks"))
+            .and(query_param_is_missing("from"))
+            .and(query_param_is_missing("to"))
+            .and(query_param_is_missing("extended"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"recenttracks": {"track": []}})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let tracks = mock_client(&server)
+            .user_recent_tracks("sebnow", None)
+            .await
+            .unwrap();
+
+        assert!(tracks.is_empty());
+    }
+
+    #[test]
+    fn deserialize_personal_tags_artists() {
+        let json = json!({"taggings": {
+            "artists": {"artist": [{
+                "name": "Bonobo",
+                "mbid": "9a709693-b4f8-4da9-8cc1-038c911a61be",
+                "url": "https://www.last.fm/music/Bonobo",
+                "streamable": "0",
+                "image": []
+            }]},
+            "@attr": {"user": "sebnow", "tag": "chill", "page": "1", "perPage": "50", "totalPages": "1", "total": "1"}
+        }});
+
+        let tags: PersonalTags = serde_json::from_value(json).unwrap();
+        let paged = tags.taggings.into_paged();
+        assert_eq!(
+            paged.items,
+            vec![TaggedItem::Artist(Artist {
+                name: "Bonobo".into(),
+                url: Some("https://www.last.fm/music/Bonobo".into()),
+                images: Default::default(),
+            })]
+        );
+        assert_eq!(paged.pagination.total, 1);
+    }
+
+    #[tokio::test]
+    async fn user_personal_tags_sends_tag_and_type() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "user.getPersonalTags"))
+            .and(query_param("tag", "chill out"))
+            .and(query_param("taggingtype", "track"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"taggings": {
+                "tracks": {"track": {
+                    "name": "Kiara",
+                    "url": "https://www.last.fm/music/Bonobo/_/Kiara",
+                    "artist": {"name": "Bonobo", "url": "https://www.last.fm/music/Bonobo"}
+                }},
+                "@attr": {"user": "sebnow", "tag": "chill out", "page": "1", "perPage": "50", "totalPages": "1", "total": "1"}
+            }})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let paged = mock_client(&server)
+            .user_personal_tags("sebnow", "chill out", TaggingType::Track, None, None)
+            .await
+            .unwrap();
+
+        assert!(
+            matches!(&paged.items[..], [TaggedItem::Track(t)] if t.name == "Kiara" && t.artist.name == "Bonobo")
+        );
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests[0].url.query().unwrap().contains("tag=chill+out"));
+    }
+
+    #[tokio::test]
+    async fn user_info_rejects_empty_user() {
+        let server = MockServer::start().await;
+        Mock::given(query_param("method", "user.getInfo"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let err = mock_client(&server).user_info("").await.unwrap_err();
+
+        assert!(matches!(err, errors::Error::InvalidInput(m) if m.contains("user")));
+    }
+}
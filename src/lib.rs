@@ -1 +1,2 @@
 pub mod lastfm;
+pub mod playlist;
@@ -0,0 +1,541 @@
+//! Assembling and exporting playlists of tracks.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lastfm::{Artist, Playlist, PlaylistItem, SimilarTrack};
+
+/// Identify a track regardless of differences in case or surrounding
+/// whitespace.
+pub(crate) fn track_key(artist: &str, track: &str) -> (String, String) {
+    (artist.trim().to_lowercase(), track.trim().to_lowercase())
+}
+
+/// The trimmed names of `artists`, in order, without repeats. Last.fm
+/// sometimes credits the same artist more than once.
+pub fn artist_names(artists: &[Artist]) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::with_capacity(artists.len());
+    for a in artists {
+        let name = a.name.trim();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+/// Combine the similar tracks of several seeds into one list, ranked by their
+/// combined match score.
+///
+/// A track's scores are summed across the lists it appears in, so tracks
+/// similar to several seeds rank above those similar to only one. Tracks are
+/// identified ignoring case and surrounding whitespace, keeping the details of
+/// their first appearance. Ties are ordered by track name, then artist.
+pub fn merge(lists: &[Vec<SimilarTrack>]) -> Vec<SimilarTrack> {
+    let mut merged: Vec<SimilarTrack> = Vec::new();
+    let mut index: HashMap<(String, String), usize> = HashMap::new();
+
+    for t in lists.iter().flatten() {
+        let key = track_key(&t.artist.name, &t.name);
+        match index.get(&key) {
+            Some(&i) => merged[i].match_score += t.match_score,
+            None => {
+                index.insert(key, merged.len());
+                merged.push(t.clone());
+            }
+        }
+    }
+
+    merged.sort_by(|a, b| {
+        b.match_score
+            .total_cmp(&a.match_score)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.artist.name.cmp(&b.artist.name))
+    });
+
+    merged
+}
+
+/// Sort tracks by their [normalized match
+/// score](SimilarTrack::normalized_match), best match first. Tracks with equal
+/// scores keep their order.
+pub fn sort_by_match(tracks: &mut [SimilarTrack]) {
+    tracks.sort_by(|a, b| b.normalized_match().total_cmp(&a.normalized_match()));
+}
+
+/// Assembles a playlist from candidate tracks, dropping duplicates and
+/// limiting how often each artist appears.
+///
+/// ```
+/// use playlie::playlist::Assembler;
+///
+/// let assembler = Assembler::new().max_per_artist(1);
+/// # let candidates = Vec::new();
+/// let playlist = assembler.assemble(candidates);
+/// # assert!(playlist.is_empty());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Assembler {
+    max_per_artist: usize,
+}
+
+impl Default for Assembler {
+    fn default() -> Self {
+        Assembler { max_per_artist: 2 }
+    }
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set how many tracks by the same artist may appear. Defaults to 2.
+    pub fn max_per_artist(mut self, max: usize) -> Self {
+        self.max_per_artist = max;
+        self
+    }
+
+    /// Keep the candidates, in order, which are neither a duplicate of an
+    /// earlier track nor over their artist's limit. Artists and tracks are
+    /// compared ignoring case and surrounding whitespace.
+    pub fn assemble(&self, candidates: Vec<SimilarTrack>) -> Vec<SimilarTrack> {
+        let mut seen = HashSet::new();
+        let mut per_artist: HashMap<String, usize> = HashMap::new();
+
+        candidates
+            .into_iter()
+            .filter(|t| {
+                let key = track_key(&t.artist.name, &t.name);
+                let count = per_artist.entry(key.0.clone()).or_default();
+                if *count >= self.max_per_artist || !seen.insert(key) {
+                    return false;
+                }
+                *count += 1;
+                true
+            })
+            .collect()
+    }
+}
+
+/// The Last.fm page of a track, used to locate tracks in exported playlists.
+/// The `url` reported by Last.fm is used where there is one, as it accounts
+/// for corrected names; otherwise the page is derived from the names.
+fn track_url(url: Option<&str>, artist: &str, track: &str) -> String {
+    if let Some(url) = url {
+        return url.to_owned();
+    }
+
+    let mut url = reqwest::Url::parse("https://www.last.fm/music").expect("valid URL");
+    url.path_segments_mut()
+        .expect("URL must be a base")
+        .extend(&[artist, "_", track]);
+
+    url.to_string()
+}
+
+/// Replace line breaks, which would end an M3U directive early.
+fn single_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+/// Export tracks as an extended M3U playlist. Each track is titled
+/// `artist - track` and located by its Last.fm page, since the tracks are not
+/// local files.
+pub fn to_m3u(tracks: &[SimilarTrack]) -> String {
+    ExportedPlaylist::from(tracks).to_m3u()
+}
+
+/// Escape the characters with special meaning in XML text and attributes.
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Export tracks as an [XSPF](https://xspf.org/) playlist, locating each
+/// track by its Last.fm page.
+pub fn to_xspf(tracks: &[SimilarTrack]) -> String {
+    let mut xspf = String::new();
+    let _ = writeln!(xspf, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        xspf,
+        r#"<playlist version="1" xmlns="http://xspf.org/ns/0/">"#
+    );
+    let _ = writeln!(xspf, "  <trackList>");
+    for t in tracks {
+        let location = track_url(t.url.as_deref(), &t.artist.name, &t.name);
+        let _ = writeln!(xspf, "    <track>");
+        let _ = writeln!(xspf, "      <location>{}</location>", xml_escape(&location));
+        let _ = writeln!(
+            xspf,
+            "      <creator>{}</creator>",
+            xml_escape(&t.artist.name)
+        );
+        let _ = writeln!(xspf, "      <title>{}</title>", xml_escape(&t.name));
+        let _ = writeln!(xspf, "    </track>");
+    }
+    let _ = writeln!(xspf, "  </trackList>");
+    let _ = writeln!(xspf, "</playlist>");
+
+    xspf
+}
+
+/// A playlist in playlie's own JSON format, independent of Last.fm's
+/// responses:
+///
+/// ```json
+/// {"tracks": [{"artists": ["Cher"], "name": "Believe", "match_score": 1.0}]}
+/// ```
+///
+/// `match_score` is omitted for tracks which were not matched against a seed,
+/// and `url` for tracks whose Last.fm page is not known.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportedPlaylist {
+    pub tracks: Vec<ExportedTrack>,
+}
+
+/// A track of an [`ExportedPlaylist`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportedTrack {
+    pub artists: Vec<String>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub match_score: Option<f64>,
+    /// The track's page on Last.fm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl From<&[SimilarTrack]> for ExportedPlaylist {
+    fn from(tracks: &[SimilarTrack]) -> Self {
+        ExportedPlaylist {
+            tracks: tracks
+                .iter()
+                .map(|t| ExportedTrack {
+                    artists: vec![t.artist.name.clone()],
+                    name: t.name.clone(),
+                    match_score: Some(t.match_score),
+                    url: t.url.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&Playlist> for ExportedPlaylist {
+    fn from(playlist: &Playlist) -> Self {
+        ExportedPlaylist {
+            tracks: playlist.playlist.iter().map(ExportedTrack::from).collect(),
+        }
+    }
+}
+
+impl From<&PlaylistItem> for ExportedTrack {
+    fn from(item: &PlaylistItem) -> Self {
+        ExportedTrack {
+            artists: artist_names(&item.artists)
+                .into_iter()
+                .map(str::to_owned)
+                .collect(),
+            name: item.name.clone(),
+            match_score: None,
+            url: item.url.clone(),
+        }
+    }
+}
+
+impl ExportedPlaylist {
+    /// Export as an extended M3U playlist, like [`to_m3u`]. Tracks by several
+    /// artists are titled with the artists joined by `&`.
+    pub fn to_m3u(&self) -> String {
+        let mut m3u = String::from("#EXTM3U\n");
+        for t in &self.tracks {
+            let artist = t.artists.first().map_or("", String::as_str);
+            // The duration is not known, which M3U represents as -1
+            let _ = writeln!(
+                m3u,
+                "#EXTINF:-1,{} - {}\n{}",
+                single_line(&t.artists.join(" & ")),
+                single_line(&t.name),
+                single_line(&track_url(t.url.as_deref(), artist, &t.name))
+            );
+        }
+
+        m3u
+    }
+
+    /// Export as a JSON document, like [`to_json`].
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("playlist is always serializable")
+    }
+}
+
+/// Export tracks as an [`ExportedPlaylist`] JSON document.
+pub fn to_json(tracks: &[SimilarTrack]) -> String {
+    ExportedPlaylist::from(tracks).to_json()
+}
+
+/// Quote a CSV field, as described by RFC 4180, if it contains a delimiter,
+/// quote, or line break.
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// Export tracks as CSV with `artist,track,match` columns and a header row.
+pub fn to_csv(tracks: &[SimilarTrack]) -> String {
+    let mut csv = String::from("artist,track,match\r\n");
+    for t in tracks {
+        let _ = write!(
+            csv,
+            "{},{},{}\r\n",
+            csv_field(&t.artist.name),
+            csv_field(&t.name),
+            t.match_score
+        );
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lastfm::PlaylistItem;
+
+    fn track(artist: &str, name: &str) -> SimilarTrack {
+        SimilarTrack {
+            name: name.into(),
+            artist: Artist {
+                name: artist.into(),
+                images: Default::default(),
+                url: None,
+            },
+            match_score: 0.5,
+            playcount: None,
+            mbid: None,
+            images: Default::default(),
+            url: None,
+        }
+    }
+
+    fn names(playlist: &[SimilarTrack]) -> Vec<&str> {
+        playlist.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    fn scored(artist: &str, name: &str, match_score: f64) -> SimilarTrack {
+        SimilarTrack {
+            match_score,
+            ..track(artist, name)
+        }
+    }
+
+    #[test]
+    fn merge_sums_scores_of_overlapping_tracks() {
+        let merged = merge(&[
+            vec![
+                scored("Cher", "Strong Enough", 0.9),
+                scored("Madonna", "Vogue", 0.6),
+                scored("ABBA", "Dancing Queen", 0.3),
+            ],
+            vec![
+                scored("Madonna", "Vogue", 0.5),
+                scored("cher ", "strong enough", 0.1),
+                scored("Kylie Minogue", "Spinning Around", 0.3),
+            ],
+        ]);
+
+        assert_eq!(
+            names(&merged),
+            vec!["Vogue", "Strong Enough", "Dancing Queen", "Spinning Around"]
+        );
+        assert!((merged[0].match_score - 1.1).abs() < 1e-9);
+        assert_eq!(merged[1].artist.name, "Cher");
+    }
+
+    #[test]
+    fn normalized_match_is_clamped() {
+        assert_eq!(scored("Cher", "Believe", 0.25).normalized_match(), 0.25);
+        assert_eq!(scored("Cher", "Believe", 1.5).normalized_match(), 1.0);
+        assert_eq!(scored("Cher", "Believe", -0.5).normalized_match(), 0.0);
+        assert_eq!(scored("Cher", "Believe", f64::NAN).normalized_match(), 0.0);
+    }
+
+    #[test]
+    fn sort_by_match_orders_best_first() {
+        let mut tracks = vec![
+            scored("Cher", "Believe", 0.2),
+            scored("Cher", "Strong Enough", 3.0),
+            scored("ABBA", "Waterloo", 0.7),
+            scored("Madonna", "Vogue", 1.0),
+        ];
+
+        sort_by_match(&mut tracks);
+
+        assert_eq!(
+            names(&tracks),
+            vec!["Strong Enough", "Vogue", "Waterloo", "Believe"]
+        );
+    }
+
+    #[test]
+    fn caps_tracks_per_artist() {
+        let candidates = vec![
+            track("Cher", "Believe"),
+            track("Cher", "Strong Enough"),
+            track("Madonna", "Vogue"),
+            track("Cher", "If I Could Turn Back Time"),
+            track("cher ", "Gypsys, Tramps & Thieves"),
+            track("Cher", "The Shoop Shoop Song"),
+        ];
+
+        let playlist = Assembler::new().assemble(candidates);
+
+        assert_eq!(names(&playlist), vec!["Believe", "Strong Enough", "Vogue"]);
+    }
+
+    #[test]
+    fn removes_duplicates() {
+        let candidates = vec![
+            track("Cher", "Believe"),
+            track(" CHER", "believe "),
+            track("Cher", "Strong Enough"),
+        ];
+
+        let playlist = Assembler::new().max_per_artist(5).assemble(candidates);
+
+        assert_eq!(names(&playlist), vec!["Believe", "Strong Enough"]);
+    }
+
+    #[test]
+    fn exports_m3u() {
+        let tracks = vec![
+            track("Cher", "Believe"),
+            track("Simon & Garfunkel", "Mrs. Robinson\n#EXTM3U"),
+        ];
+
+        assert_eq!(
+            to_m3u(&tracks),
+            "#EXTM3U\n\
+             #EXTINF:-1,Cher - Believe\n\
+             https://www.last.fm/music/Cher/_/Believe\n\
+             #EXTINF:-1,Simon & Garfunkel - Mrs. Robinson #EXTM3U\n\
+             https://www.last.fm/music/Simon%20&%20Garfunkel/_/Mrs.%20Robinson%23EXTM3U\n"
+        );
+    }
+
+    #[test]
+    fn exports_reported_urls() {
+        let url = "https://www.last.fm/music/Dolly+Parton/_/Islands+in+the+Stream";
+        let tracks = vec![SimilarTrack {
+            url: Some(url.into()),
+            ..track("Dolly Parton & Kenny Rogers", "Islands in the Stream")
+        }];
+
+        assert_eq!(
+            to_m3u(&tracks),
+            format!(
+                "#EXTM3U\n\
+                 #EXTINF:-1,Dolly Parton & Kenny Rogers - Islands in the Stream\n\
+                 {}\n",
+                url
+            )
+        );
+        assert!(to_xspf(&tracks).contains(&format!("<location>{}</location>", url)));
+    }
+
+    #[test]
+    fn exports_xspf_escaping_names() {
+        let tracks = vec![track("Simon & Garfunkel", "<Mrs. Robinson>")];
+
+        assert_eq!(
+            to_xspf(&tracks),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <trackList>
+    <track>
+      <location>https://www.last.fm/music/Simon%20&amp;%20Garfunkel/_/%3CMrs.%20Robinson%3E</location>
+      <creator>Simon &amp; Garfunkel</creator>
+      <title>&lt;Mrs. Robinson&gt;</title>
+    </track>
+  </trackList>
+</playlist>
+"#
+        );
+    }
+
+    #[test]
+    fn exports_json() {
+        let tracks = vec![track("Cher", "Believe")];
+
+        assert_eq!(
+            to_json(&tracks),
+            r#"{"tracks":[{"artists":["Cher"],"name":"Believe","match_score":0.5}]}"#
+        );
+    }
+
+    #[test]
+    fn exported_playlist_round_trips() {
+        let playlist = Playlist {
+            playlist: vec![PlaylistItem {
+                name: "Islands in the Stream".into(),
+                artists: vec![
+                    Artist {
+                        name: "Dolly Parton".into(),
+                        images: Default::default(),
+                        url: None,
+                    },
+                    Artist {
+                        name: "Kenny Rogers".into(),
+                        images: Default::default(),
+                        url: None,
+                    },
+                ],
+                url: None,
+            }],
+        };
+        let exported = ExportedPlaylist::from(&playlist);
+
+        let json = serde_json::to_string(&exported).unwrap();
+        assert_eq!(
+            json,
+            r#"{"tracks":[{"artists":["Dolly Parton","Kenny Rogers"],"name":"Islands in the Stream"}]}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<ExportedPlaylist>(&json).unwrap(),
+            exported
+        );
+    }
+
+    #[test]
+    fn exports_csv_quoting_fields() {
+        let tracks = vec![
+            track("Cher", "Gypsys, Tramps & Thieves"),
+            track("The \"Weird Al\" Band", "Eat It"),
+        ];
+
+        assert_eq!(
+            to_csv(&tracks),
+            "artist,track,match\r\n\
+             Cher,\"Gypsys, Tramps & Thieves\",0.5\r\n\
+             \"The \"\"Weird Al\"\" Band\",Eat It,0.5\r\n"
+        );
+    }
+}